@@ -4,6 +4,8 @@
 // re-exports
 pub use datetime::*;
 pub use macros::*;
+pub use map::*;
+pub use parsed::*;
 pub use toml::value::{Date, Datetime, Offset, Time};
 
 #[cfg(feature = "phf")]
@@ -35,6 +37,521 @@ macro_rules! toml_const_ws {
     };
 }
 
+/// Error returned when parsing a string that is not a value of a generated enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownVariant;
+
+impl core::fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("unknown enum variant")
+    }
+}
+
+/// Implementation details of `#[derive(TomlConst)]` and `#[map]`.
+#[doc(hidden)]
+pub mod __private {
+    use core::{
+        ffi::CStr,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+        num::{
+            NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+            NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+        },
+        time::Duration,
+    };
+
+    /// A single character of a field name, encoded as a type.
+    pub struct Ch<const C: char>;
+
+    /// Type of the field `Name` in a struct, where `Name` is a tuple of [Ch].
+    #[diagnostic::on_unimplemented(message = "`{Self}` does not have a field for a key")]
+    pub trait FieldType<Name> {
+        type Ty;
+    }
+
+    /// Element type of an array.
+    pub trait ElementType {
+        type Ty;
+    }
+
+    impl<T: 'static> ElementType for &'static [T] {
+        type Ty = T;
+    }
+
+    impl<T, const N: usize> ElementType for [T; N] {
+        type Ty = T;
+    }
+
+    /// Field names of a struct, in the order they are declared.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` does not list its fields",
+        note = "derive `TomlConst` for `{Self}`, or list the fields, e.g. `Type {{ a, b }}`"
+    )]
+    pub trait Fields {
+        const FIELDS: &'static [&'static str];
+    }
+
+    /// Panics if the keys of a table do not match the fields of its struct,
+    /// listing missing and extra keys after `context`.
+    pub const fn check_keys(context: &str, keys: &[&str], fields: &[&str]) {
+        let mut msg = Message::new().push(context).push(":");
+        let mut mismatch = false;
+
+        let mut i = 0;
+        while i < fields.len() {
+            if !contains(keys, fields[i]) {
+                msg = match mismatch {
+                    true => msg.push(", `"),
+                    false => msg.push(" missing keys `"),
+                };
+                msg = msg.push(fields[i]).push("`");
+                mismatch = true;
+            }
+            i += 1;
+        }
+
+        let mut extra = false;
+        let mut i = 0;
+        while i < keys.len() {
+            if !contains(fields, keys[i]) {
+                msg = match (extra, mismatch) {
+                    (true, _) => msg.push(", `"),
+                    (false, true) => msg.push(", extra keys `"),
+                    (false, false) => msg.push(" extra keys `"),
+                };
+                msg = msg.push(keys[i]).push("`");
+                extra = true;
+            }
+            i += 1;
+        }
+
+        if mismatch || extra {
+            panic!("{}", msg.as_str());
+        }
+    }
+
+    /// Returns true if a list of names contains a name.
+    const fn contains(names: &[&str], name: &str) -> bool {
+        let mut i = 0;
+        while i < names.len() {
+            if str_eq(names[i], name) {
+                return true;
+            }
+            i += 1;
+        }
+
+        false
+    }
+
+    const fn str_eq(a: &str, b: &str) -> bool {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        if a.len() != b.len() {
+            return false;
+        }
+
+        let mut i = 0;
+        while i < a.len() {
+            if a[i] != b[i] {
+                return false;
+            }
+            i += 1;
+        }
+
+        true
+    }
+
+    /// A panic message built at compile time, truncated to a fixed length.
+    struct Message {
+        buf: [u8; 512],
+        len: usize,
+    }
+
+    impl Message {
+        const fn new() -> Self {
+            Self {
+                buf: [0; 512],
+                len: 0,
+            }
+        }
+
+        const fn push(mut self, s: &str) -> Self {
+            let bytes = s.as_bytes();
+            let mut i = 0;
+            while i < bytes.len() && self.len < self.buf.len() {
+                self.buf[self.len] = bytes[i];
+                self.len += 1;
+                i += 1;
+            }
+
+            self
+        }
+
+        const fn as_str(&self) -> &str {
+            let (bytes, _) = self.buf.split_at(self.len);
+
+            // truncation can split a character
+            match core::str::from_utf8(bytes) {
+                Ok(s) => s,
+                Err(e) => match core::str::from_utf8(bytes.split_at(e.valid_up_to()).0) {
+                    Ok(s) => s,
+                    Err(_) => "",
+                },
+            }
+        }
+    }
+
+    /// A TOML value in a nested struct, converted to every type that a field can have.
+    ///
+    /// Conversions that fail panic with the key path of the value when they are used.
+    pub trait Value {
+        const I8: i8;
+        const I16: i16;
+        const I32: i32;
+        const I64: i64;
+        const I128: i128;
+        const ISIZE: isize;
+        const U8: u8;
+        const U16: u16;
+        const U32: u32;
+        const U64: u64;
+        const U128: u128;
+        const USIZE: usize;
+        const NON_ZERO_I8: NonZeroI8;
+        const NON_ZERO_I16: NonZeroI16;
+        const NON_ZERO_I32: NonZeroI32;
+        const NON_ZERO_I64: NonZeroI64;
+        const NON_ZERO_I128: NonZeroI128;
+        const NON_ZERO_ISIZE: NonZeroIsize;
+        const NON_ZERO_U8: NonZeroU8;
+        const NON_ZERO_U16: NonZeroU16;
+        const NON_ZERO_U32: NonZeroU32;
+        const NON_ZERO_U64: NonZeroU64;
+        const NON_ZERO_U128: NonZeroU128;
+        const NON_ZERO_USIZE: NonZeroUsize;
+        const F32: f32;
+        const F64: f64;
+        const BOOL: bool;
+        const STR: &'static str;
+        const C_STR: &'static CStr;
+        const DURATION: Duration;
+        const IP_ADDR: IpAddr;
+        const IPV4_ADDR: Ipv4Addr;
+        const IPV6_ADDR: Ipv6Addr;
+        const SOCKET_ADDR: SocketAddr;
+        const VERSION: crate::Version;
+        const RGB: crate::Rgb;
+        const RGBA: crate::Rgba;
+        const OFFSET_DATE_TIME: crate::OffsetDateTime;
+        const LOCAL_DATE_TIME: crate::LocalDateTime;
+        const LOCAL_DATE: crate::LocalDate;
+        const LOCAL_TIME: crate::LocalTime;
+    }
+
+    /// A TOML array in a nested struct, or a string as bytes.
+    pub trait Array<E: 'static> {
+        const SLICE: &'static [E];
+    }
+
+    /// A TOML array in a nested struct with its length, or a string as bytes.
+    #[diagnostic::on_unimplemented(
+        message = "the array does not have {N} elements",
+        label = "the TOML array has a different length than the field type"
+    )]
+    pub trait FixedArray<E, const N: usize> {
+        const ARRAY: [E; N];
+    }
+
+    /// Conversion of a nested value to the declared type of its field.
+    pub trait FromToml<V> {
+        const VALUE: Self;
+    }
+
+    macro_rules! from_value {
+        ($($ty:ty => $value:ident),* $(,)?) => {
+            $(
+                impl<V: Value> FromToml<V> for $ty {
+                    const VALUE: Self = V::$value;
+                }
+            )*
+        };
+    }
+
+    from_value! {
+        i8 => I8, i16 => I16, i32 => I32, i64 => I64, i128 => I128, isize => ISIZE,
+        u8 => U8, u16 => U16, u32 => U32, u64 => U64, u128 => U128, usize => USIZE,
+        NonZeroI8 => NON_ZERO_I8, NonZeroI16 => NON_ZERO_I16, NonZeroI32 => NON_ZERO_I32,
+        NonZeroI64 => NON_ZERO_I64, NonZeroI128 => NON_ZERO_I128, NonZeroIsize => NON_ZERO_ISIZE,
+        NonZeroU8 => NON_ZERO_U8, NonZeroU16 => NON_ZERO_U16, NonZeroU32 => NON_ZERO_U32,
+        NonZeroU64 => NON_ZERO_U64, NonZeroU128 => NON_ZERO_U128, NonZeroUsize => NON_ZERO_USIZE,
+        f32 => F32, f64 => F64, bool => BOOL, &'static str => STR, &'static CStr => C_STR,
+        Duration => DURATION, IpAddr => IP_ADDR, Ipv4Addr => IPV4_ADDR, Ipv6Addr => IPV6_ADDR,
+        SocketAddr => SOCKET_ADDR, crate::Version => VERSION, crate::Rgb => RGB, crate::Rgba => RGBA,
+        crate::OffsetDateTime => OFFSET_DATE_TIME, crate::LocalDateTime => LOCAL_DATE_TIME,
+        crate::LocalDate => LOCAL_DATE, crate::LocalTime => LOCAL_TIME,
+    }
+
+    impl<E: 'static, V: Array<E>> FromToml<V> for &'static [E] {
+        const VALUE: Self = V::SLICE;
+    }
+
+    impl<E, const N: usize, V: FixedArray<E, N>> FromToml<V> for [E; N] {
+        const VALUE: Self = V::ARRAY;
+    }
+}
+
+/// Structs for values parsed from strings
+mod parsed {
+    use core::cmp::Ordering;
+
+    /// A semantic version, e.g. `1.2.3-beta.1+build.5`.
+    ///
+    /// Versions are ordered by semver precedence. Build metadata does not affect precedence,
+    /// and is only compared to break ties.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct Version {
+        pub major: u64,
+        pub minor: u64,
+        pub patch: u64,
+        /// Pre-release identifiers, or an empty string.
+        pub pre: &'static str,
+        /// Build metadata, or an empty string.
+        pub build: &'static str,
+    }
+
+    /// An RGB color, e.g. `#ff8800`.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Rgb {
+        pub r: u8,
+        pub g: u8,
+        pub b: u8,
+    }
+
+    /// An RGB color with an alpha channel, e.g. `#ff880080`.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct Rgba {
+        pub r: u8,
+        pub g: u8,
+        pub b: u8,
+        pub a: u8,
+    }
+
+    impl Version {
+        /// Returns true if this is a pre-release version.
+        pub const fn is_prerelease(&self) -> bool {
+            !self.pre.is_empty()
+        }
+    }
+
+    impl Ord for Version {
+        fn cmp(&self, other: &Self) -> Ordering {
+            (self.major, self.minor, self.patch)
+                .cmp(&(other.major, other.minor, other.patch))
+                .then_with(|| cmp_pre(self.pre, other.pre))
+                .then_with(|| self.build.cmp(other.build))
+        }
+    }
+
+    impl PartialOrd for Version {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    /// Compare pre-release identifiers by semver precedence.
+    fn cmp_pre(a: &str, b: &str) -> Ordering {
+        match (a.is_empty(), b.is_empty()) {
+            (true, true) => return Ordering::Equal,
+            // releases have a higher precedence than pre-releases
+            (true, false) => return Ordering::Greater,
+            (false, true) => return Ordering::Less,
+            (false, false) => (),
+        }
+
+        let is_numeric = |id: &str| id.bytes().all(|b| b.is_ascii_digit());
+        let mut a_ids = a.split('.');
+        let mut b_ids = b.split('.');
+
+        loop {
+            let ordering = match (a_ids.next(), b_ids.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(a), Some(b)) => match (is_numeric(a), is_numeric(b)) {
+                    // numeric identifiers do not have leading zeros
+                    (true, true) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+                    (true, false) => Ordering::Less,
+                    (false, true) => Ordering::Greater,
+                    (false, false) => a.cmp(b),
+                },
+            };
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+    }
+
+    impl core::fmt::Display for Version {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+            if !self.pre.is_empty() {
+                write!(f, "-{}", self.pre)?;
+            }
+            if !self.build.is_empty() {
+                write!(f, "+{}", self.build)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl core::fmt::Display for Rgb {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+        }
+    }
+
+    impl core::fmt::Display for Rgba {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(
+                f,
+                "#{:02x}{:02x}{:02x}{:02x}",
+                self.r, self.g, self.b, self.a
+            )
+        }
+    }
+}
+
+/// Lookup of table maps without hashing
+mod map {
+    use core::cmp::Ordering;
+
+    /// Entries of a table map, sorted by key, that can be searched in const contexts.
+    ///
+    /// Keys are sorted by their bytes, so entries are iterated in that order
+    /// instead of the order of the TOML file.
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    pub struct ConstMap<V: 'static> {
+        entries: &'static [(&'static str, V)],
+    }
+
+    impl<V> Clone for ConstMap<V> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<V> Copy for ConstMap<V> {}
+
+    impl<V> ConstMap<V> {
+        /// Entries must be sorted by key, without duplicates.
+        #[doc(hidden)]
+        pub const fn new(entries: &'static [(&'static str, V)]) -> Self {
+            Self { entries }
+        }
+
+        /// The value of a key, if it exists.
+        pub const fn get(&self, key: &str) -> Option<&'static V> {
+            match self.get_entry(key) {
+                Some((_, value)) => Some(value),
+                None => None,
+            }
+        }
+
+        /// The key and value of a key, if it exists.
+        pub const fn get_entry(&self, key: &str) -> Option<&'static (&'static str, V)> {
+            let entries = self.entries;
+            let key = key.as_bytes();
+
+            let mut low = 0;
+            let mut high = entries.len();
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match compare(entries[mid].0.as_bytes(), key) {
+                    Ordering::Less => low = mid + 1,
+                    Ordering::Greater => high = mid,
+                    Ordering::Equal => return Some(&entries[mid]),
+                }
+            }
+
+            None
+        }
+
+        /// Returns true if the key exists.
+        pub const fn contains_key(&self, key: &str) -> bool {
+            self.get_entry(key).is_some()
+        }
+
+        /// The number of entries.
+        pub const fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        /// Returns true if there are no entries.
+        pub const fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        /// All entries, sorted by key.
+        pub const fn entries(&self) -> &'static [(&'static str, V)] {
+            self.entries
+        }
+
+        /// Iterate over all entries, sorted by key.
+        pub fn iter(&self) -> core::slice::Iter<'static, (&'static str, V)> {
+            self.entries.iter()
+        }
+
+        /// Iterate over all keys, in sorted order.
+        pub fn keys(&self) -> impl Iterator<Item = &'static str> {
+            self.entries.iter().map(|(key, _)| *key)
+        }
+
+        /// Iterate over all values, sorted by their keys.
+        pub fn values(&self) -> impl Iterator<Item = &'static V> {
+            self.entries.iter().map(|(_, value)| value)
+        }
+    }
+
+    impl<V> IntoIterator for ConstMap<V> {
+        type Item = &'static (&'static str, V);
+        type IntoIter = core::slice::Iter<'static, (&'static str, V)>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    impl<V> IntoIterator for &ConstMap<V> {
+        type Item = &'static (&'static str, V);
+        type IntoIter = core::slice::Iter<'static, (&'static str, V)>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.iter()
+        }
+    }
+
+    /// Compare byte strings in const contexts, where [Ord] cannot be used.
+    const fn compare(a: &[u8], b: &[u8]) -> Ordering {
+        let mut i = 0;
+        while i < a.len() && i < b.len() {
+            if a[i] != b[i] {
+                return match a[i] < b[i] {
+                    true => Ordering::Less,
+                    false => Ordering::Greater,
+                };
+            }
+            i += 1;
+        }
+
+        match (a.len() < b.len(), a.len() > b.len()) {
+            (true, _) => Ordering::Less,
+            (_, true) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
 /// Destructured datetime structs
 mod datetime {
     use super::*;
@@ -2,11 +2,6 @@
 
 use std::collections::HashSet;
 
-// use proc_macro::Span;
-use proc_macro2::{self as pm2, Span};
-
-use crate::MAP_FIELD;
-
 /// Various ways checks can be mismatched
 #[derive(Clone, Debug)]
 pub enum CheckError {
@@ -77,40 +72,6 @@ impl std::error::Error for CheckError {
     }
 }
 
-/// Check that this table and all child items do not contain prohibited keys.
-pub fn check_unauthorized_keys(input: &toml::Table) -> Result<(), pm2::TokenStream> {
-    for (key, value) in input.iter() {
-        if key.is_empty() {
-            return Err(
-                syn::Error::new(Span::call_site(), "empty quoted keys cannot be used")
-                    .to_compile_error(),
-            );
-        }
-
-        if key == MAP_FIELD {
-            return Err(syn::Error::new(
-                Span::call_site(),
-                format!("\"{MAP_FIELD}\" is a reserved key"),
-            )
-            .to_compile_error());
-        }
-
-        match value {
-            toml::Value::Table(sub_table) => check_unauthorized_keys(sub_table)?,
-            toml::Value::Array(arr) => {
-                for item in arr.iter() {
-                    if let toml::Value::Table(sub_table) = item {
-                        check_unauthorized_keys(sub_table)?
-                    }
-                }
-            }
-            _ => (),
-        }
-    }
-
-    Ok(())
-}
-
 /// Main check entry point
 #[allow(unused)]
 fn check(table: &toml::Table) -> Result<(), CheckError> {
@@ -193,7 +154,7 @@ fn compare_value(
 #[allow(unused)]
 fn check_array_schema(arr: &toml::value::Array) -> Result<(), CheckError> {
     match arr.len() {
-        0..2 => (),
+        0..=1 => (),
         _ => {
             let mut arr_iter = arr.iter();
             let first = arr_iter.next().unwrap();
@@ -295,51 +256,6 @@ mod tests {
     use super::*;
     use toml::de::from_str;
 
-    #[test]
-    fn test_check_unauthorized_keys() {
-        let toml_str = r#"
-            [a]
-            key1 = "value1"
-            key2 = 42
-            key3 = true
-
-            [[b]]
-            key1 = "value1"
-
-            [[b]]
-            key1 = "value2"
-
-            [c]
-            subtable.item = "value3"
-            subtable.otheritem = "value4"
-        "#;
-
-        let table: toml::Table = from_str(toml_str).unwrap();
-        assert!(check_unauthorized_keys(&table).is_ok());
-
-        // Check with an empty key
-        let toml_str_with_empty_key = r#"
-            [a]
-            "" = "value1"
-            key2 = 42
-            key3 = true
-
-            [[b]]
-            key1 = "value1"
-
-            [[b]]
-            key1 = "value2"
-
-            [c]
-            subtable.item = "value3"
-            subtable.otheritem = "value4"
-        "#;
-
-        let table_with_empty_key: toml::Table = from_str(toml_str_with_empty_key).unwrap();
-        let res = check_unauthorized_keys(&table_with_empty_key);
-        assert!(res.is_err());
-    }
-
     #[test]
     fn test_check_matching_schema() {
         let toml_a = r#"
@@ -0,0 +1,610 @@
+//! `#[derive(TomlConst)]`: instantiate a user-defined struct from a TOML file.
+//!
+//! The struct is the schema. Field types are declared in rust code instead of inferred from the file.
+//!
+//! Types of nested tables are not visible to the derive of the outer struct.
+//! Each derived struct lists its fields with `Fields`, and implements `FieldType<Name>` for them,
+//! where `Name` is the field name encoded as a tuple of `Ch<'c'>`.
+//! Nested tables are instantiated through these projections,
+//! e.g. `<Server as FieldType<(Ch<'t'>, Ch<'l'>, Ch<'s'>)>>::Ty { .. }`.
+//!
+//! Other nested values are converted with `<Ty as FromToml<V>>::VALUE`, where `V` is a local type
+//! whose `Value` constants hold the value converted to every field type that it can have.
+//! Conversions that fail are panics naming the key, which are only evaluated for the declared type.
+
+use std::path::PathBuf;
+
+use proc_macro2::{self as pm2, Span};
+use quote::{format_ident, quote, ToTokens};
+use syn::{ext::IdentExt, spanned::Spanned, LitStr};
+
+use crate::{
+    instantiate::{ConstIdentDef, Instantiate},
+    normalize::TomlValue,
+    options::Naming,
+    types::{IntType, ValueType},
+};
+
+/// Helper attribute on the derived struct.
+const ATTR: &str = "toml_const";
+
+/// `#[toml_const(path = "config.toml", crate = toml_const)]`
+struct DeriveOptions {
+    /// Path to the TOML file, relative to the crate's `Cargo.toml`.
+    /// Structs without a path can only be used as nested tables.
+    path: Option<LitStr>,
+    runtime_path: syn::Path,
+}
+
+/// Instantiates values whose types are only known through projections.
+struct Emitter<'a> {
+    runtime_path: &'a syn::Path,
+    /// Path of the TOML file, as written in the attribute
+    file: String,
+    /// Type aliases for tables, so that they can be instantiated with struct literals,
+    /// and types that convert other values to their field types
+    items: Vec<pm2::TokenStream>,
+}
+
+/// Implementation of `#[derive(TomlConst)]`.
+pub fn derive_toml_const(input: syn::DeriveInput) -> Result<pm2::TokenStream, syn::Error> {
+    let options = DeriveOptions::from_attrs(&input.attrs)?;
+    let runtime_path = &options.runtime_path;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "#[derive(TomlConst)] does not support generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(fields),
+            ..
+        }) => &fields.named,
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "#[derive(TomlConst)] only supports structs with named fields",
+            ))
+        }
+    };
+
+    let ident = &input.ident;
+    let field_type_impls = fields
+        .iter()
+        .map(|field| {
+            let name = field_name(field);
+            let name_ty = field_name_type(&name, runtime_path);
+            let ty = &field.ty;
+
+            quote! {
+                impl #runtime_path::__private::FieldType<#name_ty> for #ident {
+                    type Ty = #ty;
+                }
+            }
+        })
+        .collect::<pm2::TokenStream>();
+    let field_names = fields.iter().map(field_name);
+    let field_type_impls = quote! {
+        #field_type_impls
+
+        impl #runtime_path::__private::Fields for #ident {
+            const FIELDS: &'static [&'static str] = &[#(#field_names),*];
+        }
+    };
+
+    let path = match &options.path {
+        Some(path) => path,
+        None => return Ok(field_type_impls),
+    };
+
+    let abs_path = abs_path(path)?;
+    let abs_path_str = abs_path.to_string_lossy().to_string();
+    let contents = std::fs::read_to_string(&abs_path).map_err(|e| {
+        syn::Error::new(
+            path.span(),
+            format!("failed to read {}: {}", abs_path_str, e),
+        )
+    })?;
+    let table: toml::Table = toml::from_str(&contents).map_err(|e| {
+        syn::Error::new(
+            path.span(),
+            format!("failed to parse {}: {}", abs_path_str, e),
+        )
+    })?;
+
+    // keys that do not match a field
+    let extra_keys = table
+        .keys()
+        .filter(|k| !fields.iter().any(|f| field_name(f) == key_field_name(k)))
+        .map(|k| format!("`{}`", k))
+        .collect::<Vec<_>>();
+    if !extra_keys.is_empty() {
+        return Err(syn::Error::new(
+            path.span(),
+            format!(
+                "keys {} in {} do not match any field of {}",
+                extra_keys.join(", "),
+                path.value(),
+                ident
+            ),
+        ));
+    }
+
+    let mut emitter = Emitter {
+        runtime_path,
+        file: path.value(),
+        items: Vec::new(),
+    };
+    let field_values = fields
+        .iter()
+        .map(|field| {
+            let name = field_name(field);
+            let field_ident = field.ident.as_ref().expect("fields are named");
+
+            let (key, value) = table
+                .iter()
+                .find(|(k, _)| key_field_name(k) == name)
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        field_ident.span(),
+                        format!("key `{}` is missing from {}", name, path.value()),
+                    )
+                })?;
+
+            let value = emitter.field_value(key, value, &field.ty)?;
+            Ok(quote! { #field_ident: #value })
+        })
+        .collect::<Result<Vec<_>, syn::Error>>()?;
+
+    let items = &emitter.items;
+    let doc = format!("Contents of `{}`.", path.value());
+
+    Ok(quote! {
+        #field_type_impls
+
+        // rebuild when the file changes
+        const _: &str = include_str!(#abs_path_str);
+
+        impl #ident {
+            #[doc = #doc]
+            pub const VALUE: Self = {
+                #(#items)*
+
+                #ident {
+                    #(#field_values),*
+                }
+            };
+        }
+    })
+}
+
+impl DeriveOptions {
+    fn from_attrs(attrs: &[syn::Attribute]) -> Result<Self, syn::Error> {
+        let mut options = Self {
+            path: None,
+            runtime_path: syn::parse_quote!(::toml_const),
+        };
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident(ATTR)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("path") {
+                    options.path = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("crate") {
+                    options.runtime_path = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `path` or `crate`"))
+                }
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+impl Emitter<'_> {
+    /// Instantiate a top-level field, where the declared type is known.
+    fn field_value(
+        &mut self,
+        key: &str,
+        value: &toml::Value,
+        ty: &syn::Type,
+    ) -> Result<pm2::TokenStream, syn::Error> {
+        let error = |msg: String| syn::Error::new(ty.span(), format!("{}: {}", key, msg));
+
+        // `&[u8]` fields are byte strings only if the value is a string
+        let value_type = ValueType::from_type(ty)
+            .ok()
+            .filter(|t| *t != ValueType::ByteStr || value.is_str());
+        if let Some(value_type) = value_type {
+            return value_type
+                .instantiate(key, value, self.runtime_path)
+                .map_err(error);
+        }
+
+        match (ty, value) {
+            (syn::Type::Reference(reference), value) if is_ident(&reference.elem, "str") => {
+                match value {
+                    toml::Value::String(s) => Ok(quote! { #s }),
+                    other => Err(error(format!(
+                        "expected a string, found {}",
+                        other.type_str()
+                    ))),
+                }
+            }
+            (ty, value) if is_ident(ty, "bool") => match value {
+                toml::Value::Boolean(b) => Ok(quote! { #b }),
+                other => Err(error(format!(
+                    "expected a boolean, found {}",
+                    other.type_str()
+                ))),
+            },
+            (syn::Type::Array(array), toml::Value::Array(values)) => {
+                let elements = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        self.value(&format!("{}[{}]", key, i), v, array.elem.to_token_stream())
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(quote! { [#(#elements),*] })
+            }
+            (ty, value) => Ok(self.value(key, value, ty.to_token_stream())),
+        }
+    }
+
+    /// Instantiate a value whose type is given by a type expression.
+    ///
+    /// Tables are instantiated with struct literals. Other values are converted with
+    /// `FromToml`, from a type that implements `Value`, `Array` or `FixedArray` for this value.
+    fn value(&mut self, key: &str, value: &toml::Value, ty: pm2::TokenStream) -> pm2::TokenStream {
+        let runtime_path = self.runtime_path;
+
+        match value {
+            toml::Value::Table(table) => {
+                let alias = format_ident!("__T{}", self.items.len());
+
+                // the fields of nested structs are only known through `Fields`
+                let context = format!(
+                    "{}: keys in {} do not match the fields of its struct",
+                    key, self.file
+                );
+                let names = table.keys().map(|k| key_field_name(k));
+                self.items.push(quote! {
+                    type #alias = #ty;
+
+                    const _: () = #runtime_path::__private::check_keys(
+                        #context,
+                        &[#(#names),*],
+                        <#alias as #runtime_path::__private::Fields>::FIELDS,
+                    );
+                });
+
+                let fields = table
+                    .iter()
+                    .map(|(k, v)| {
+                        let field_ident = key_field_ident(k);
+                        let name_ty = field_name_type(&key_field_name(k), runtime_path);
+                        let field_ty = quote! {
+                            <#alias as #runtime_path::__private::FieldType<#name_ty>>::Ty
+                        };
+                        let field_value = self.value(&format!("{}.{}", key, k), v, field_ty);
+
+                        quote! { #field_ident: #field_value }
+                    })
+                    .collect::<Vec<_>>();
+
+                quote! { #alias { #(#fields),* } }
+            }
+            toml::Value::Array(values) => {
+                let element_ty = quote! { <#ty as #runtime_path::__private::ElementType>::Ty };
+                let elements = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| self.value(&format!("{}[{}]", key, i), v, element_ty.clone()))
+                    .collect::<Vec<_>>();
+
+                let value_ident = format_ident!("__V{}", self.items.len());
+                let len = values.len();
+                self.items.push(quote! {
+                    struct #value_ident;
+
+                    impl #runtime_path::__private::Array<#element_ty> for #value_ident {
+                        const SLICE: &'static [#element_ty] = &[#(#elements),*];
+                    }
+
+                    impl #runtime_path::__private::FixedArray<#element_ty, #len> for #value_ident {
+                        const ARRAY: [#element_ty; #len] = [#(#elements),*];
+                    }
+                });
+
+                quote! { <#ty as #runtime_path::__private::FromToml<#value_ident>>::VALUE }
+            }
+            value => {
+                let value_ident = format_ident!("__V{}", self.items.len());
+                let consts = value_consts(key, value, runtime_path);
+
+                // strings are also byte arrays
+                let bytes = value.as_str().map(|s| {
+                    let lit = syn::LitByteStr::new(s.as_bytes(), Span::call_site());
+                    let len = s.len();
+
+                    quote! {
+                        impl #runtime_path::__private::Array<u8> for #value_ident {
+                            const SLICE: &'static [u8] = #lit;
+                        }
+
+                        impl #runtime_path::__private::FixedArray<u8, #len> for #value_ident {
+                            const ARRAY: [u8; #len] = *#lit;
+                        }
+                    }
+                });
+
+                self.items.push(quote! {
+                    struct #value_ident;
+
+                    impl #runtime_path::__private::Value for #value_ident {
+                        #(#consts)*
+                    }
+
+                    #bytes
+                });
+
+                quote! { <#ty as #runtime_path::__private::FromToml<#value_ident>>::VALUE }
+            }
+        }
+    }
+}
+
+/// Constants of `Value` for a value that is not an array or a table.
+///
+/// Each constant is the value converted to one field type, or a panic that names the key.
+fn value_consts(key: &str, value: &toml::Value, runtime_path: &syn::Path) -> Vec<pm2::TokenStream> {
+    let convert =
+        |name: String, ty: pm2::TokenStream, converted: Result<pm2::TokenStream, String>| {
+            let name = syn::Ident::new(&name, Span::call_site());
+            let converted = converted.unwrap_or_else(|msg| {
+                let msg = format!("{}: {}", key, msg);
+                quote! { panic!(#msg) }
+            });
+
+            quote! { const #name: #ty = #converted; }
+        };
+
+    let value_types = IntType::ALL
+        .iter()
+        .flat_map(|int| [ValueType::Int(*int), ValueType::NonZero(*int)])
+        .chain([
+            ValueType::F32,
+            ValueType::F64,
+            ValueType::CStr,
+            ValueType::Duration,
+            ValueType::IpAddr,
+            ValueType::Ipv4Addr,
+            ValueType::Ipv6Addr,
+            ValueType::SocketAddr,
+            ValueType::Version,
+            ValueType::Rgb,
+            ValueType::Rgba,
+        ]);
+    let mut consts = value_types
+        .map(|value_type| {
+            let name = match value_type {
+                ValueType::NonZero(int) => {
+                    format!(
+                        "NON_ZERO_{}",
+                        ValueType::Int(int).to_string().to_uppercase()
+                    )
+                }
+                ValueType::CStr => "C_STR".to_string(),
+                ValueType::IpAddr => "IP_ADDR".to_string(),
+                ValueType::Ipv4Addr => "IPV4_ADDR".to_string(),
+                ValueType::Ipv6Addr => "IPV6_ADDR".to_string(),
+                ValueType::SocketAddr => "SOCKET_ADDR".to_string(),
+                other => other.to_string().to_uppercase(),
+            };
+
+            convert(
+                name,
+                value_type.ty(runtime_path),
+                value_type.instantiate(key, value, runtime_path),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    consts.push(convert(
+        "BOOL".to_string(),
+        quote! { bool },
+        match value {
+            toml::Value::Boolean(b) => Ok(quote! { #b }),
+            other => Err(format!("expected a boolean, found {}", other.type_str())),
+        },
+    ));
+    consts.push(convert(
+        "STR".to_string(),
+        quote! { &'static str },
+        match value {
+            toml::Value::String(s) => Ok(quote! { #s }),
+            other => Err(format!("expected a string, found {}", other.type_str())),
+        },
+    ));
+
+    // datetimes convert only to the type with the same components
+    let datetime_kind = match value {
+        toml::Value::Datetime(datetime) => match (datetime.date, datetime.time, datetime.offset) {
+            (Some(_), Some(_), Some(_)) => Some("OffsetDateTime"),
+            (Some(_), Some(_), None) => Some("LocalDateTime"),
+            (Some(_), None, None) => Some("LocalDate"),
+            _ => Some("LocalTime"),
+        },
+        _ => None,
+    };
+    for (name, ty) in [
+        ("OFFSET_DATE_TIME", "OffsetDateTime"),
+        ("LOCAL_DATE_TIME", "LocalDateTime"),
+        ("LOCAL_DATE", "LocalDate"),
+        ("LOCAL_TIME", "LocalTime"),
+    ] {
+        let converted = match (value, datetime_kind) {
+            (toml::Value::Datetime(datetime), Some(kind)) if kind == ty => Ok(datetime
+                .instantiate(
+                    "",
+                    &TomlValue::Boolean,
+                    vec![],
+                    runtime_path,
+                    &Naming::default(),
+                )),
+            (toml::Value::Datetime(_), Some(kind)) => {
+                Err(format!("expected {}, found {}", ty, kind))
+            }
+            (other, _) => Err(format!("expected {}, found {}", ty, other.type_str())),
+        };
+        let ty_ident = syn::Ident::new(ty, Span::call_site());
+
+        consts.push(convert(
+            name.to_string(),
+            quote! { #runtime_path::#ty_ident },
+            converted,
+        ));
+    }
+
+    consts
+}
+
+/// Name of a struct field, without the raw prefix.
+fn field_name(field: &syn::Field) -> String {
+    field
+        .ident
+        .as_ref()
+        .expect("fields are named")
+        .unraw()
+        .to_string()
+}
+
+/// Name of the field that a key is instantiated as.
+///
+/// Derived structs declare their own fields, so keys are always matched to snake_case fields.
+fn key_field_ident(key: &str) -> syn::Ident {
+    key.to_module_ident(&Naming::default())
+}
+
+/// Name of the field that a key is instantiated as, without the raw prefix.
+fn key_field_name(key: &str) -> String {
+    key_field_ident(key).unraw().to_string()
+}
+
+/// Encode a field name as a type, e.g. `(Ch<'i'>, Ch<'d'>,)`.
+fn field_name_type(name: &str, runtime_path: &syn::Path) -> pm2::TokenStream {
+    let chars = name.chars().map(|c| {
+        let lit = syn::LitChar::new(c, Span::call_site());
+        quote! { #runtime_path::__private::Ch<#lit> }
+    });
+
+    quote! { (#(#chars,)*) }
+}
+
+/// Returns true if the type is a single identifier, e.g. `bool`.
+fn is_ident(ty: &syn::Type, ident: &str) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident(ident))
+}
+
+/// Resolve a path relative to the crate's `Cargo.toml`.
+fn abs_path(path: &LitStr) -> Result<PathBuf, syn::Error> {
+    let manifest_path =
+        std::env::var("CARGO_MANIFEST_DIR").expect("manifest dir variable must exist");
+
+    PathBuf::from(manifest_path)
+        .join(path.value())
+        .canonicalize()
+        .map_err(|e| syn::Error::new(path.span(), format!("{}: {}", path.value(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_name_type() {
+        let runtime_path: syn::Path = syn::parse_quote!(toml_const);
+
+        assert_eq!(
+            field_name_type("id", &runtime_path).to_string(),
+            quote! { (toml_const::__private::Ch<'i'>, toml_const::__private::Ch<'d'>,) }
+                .to_string()
+        );
+        assert_eq!(key_field_name("connection-max"), "connection_max");
+    }
+
+    #[test]
+    fn test_value_consts() {
+        let runtime_path: syn::Path = syn::parse_quote!(toml_const);
+        let consts = value_consts("server.retries", &toml::Value::Integer(300), &runtime_path)
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>();
+
+        assert!(consts.contains(
+            &quote! { const U8: u8 = panic!("server.retries: 300 does not fit in u8"); }
+                .to_string()
+        ));
+        assert!(consts.contains(&quote! { const U16: u16 = 300u16; }.to_string()));
+        assert!(consts.contains(&quote! { const F64: f64 = 300f64; }.to_string()));
+        assert!(consts.contains(
+            &quote! {
+                const STR: &'static str = panic!("server.retries: expected a string, found integer");
+            }
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_nested_table_keys() {
+        let runtime_path: syn::Path = syn::parse_quote!(toml_const);
+        let mut emitter = Emitter {
+            runtime_path: &runtime_path,
+            file: "config.toml".to_string(),
+            items: Vec::new(),
+        };
+        let table: toml::Table = toml::from_str("host = \"a\"\nmax-conn = 1").unwrap();
+
+        let value = emitter.value("server", &toml::Value::Table(table), quote! { Server });
+        assert!(value.to_string().contains("r#max_conn :"));
+
+        let check = quote! {
+            const _: () = toml_const::__private::check_keys(
+                "server: keys in config.toml do not match the fields of its struct",
+                &["host", "max_conn"],
+                <__T0 as toml_const::__private::Fields>::FIELDS,
+            );
+        };
+        assert!(emitter.items[0].to_string().contains(&check.to_string()));
+    }
+
+    #[test]
+    fn test_derive_options() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[toml_const(path = "config.toml", crate = my::toml_const)]
+            struct Config {
+                port: u16,
+            }
+        };
+
+        let options = DeriveOptions::from_attrs(&input.attrs).expect("options must parse");
+        assert_eq!(
+            options.path.map(|p| p.value()).as_deref(),
+            Some("config.toml")
+        );
+        assert_eq!(options.runtime_path.segments.len(), 2);
+
+        let invalid: syn::DeriveInput = syn::parse_quote! {
+            #[toml_const(file = "config.toml")]
+            struct Config {}
+        };
+        assert!(DeriveOptions::from_attrs(&invalid.attrs).is_err());
+    }
+}
@@ -7,12 +7,15 @@ use proc_macro2::{self as pm2, Span};
 use quote::quote;
 use syn::{punctuated::Punctuated, Ident};
 
-use crate::TomlValue;
+use crate::{
+    normalize::{map_value_key, tuple_element_key, union_member_key, MemberKind},
+    options::{FieldNaming, MapInit, Naming, TypeNaming},
+    types::non_finite_float,
+    TomlValue,
+};
 
-/// Chars to replace when converting to an identifier.
-const REPLACE_CHARS: &[char] = &[
-    ' ', '-', '_', ':', '.', '/', '\\', '"', '\'', '(', ')', '=', ',',
-];
+/// Keywords that cannot be used as raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["self", "super", "crate", "Self"];
 
 /// Generate the instantiation of an item. This can be a custom struct or a simple value.
 /// If a key is provided, the instantiation will be in a field-value pair.
@@ -29,6 +32,7 @@ pub trait Instantiate {
         toml_value: &TomlValue,
         parents: Vec<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> pm2::TokenStream;
 }
 
@@ -37,21 +41,33 @@ pub trait ConstIdentDef {
     /// Create a valid variable identifier, formatted as SCREAMING_SNAKE_CASE.
     fn to_variable_ident(&self) -> syn::Ident;
 
-    /// Create a valid module identifier, formatted as snake_case.
-    fn to_module_ident(&self) -> syn::Ident {
-        syn::Ident::new_raw(
-            &self.to_variable_ident().to_string().to_lowercase(),
-            Span::call_site(),
-        )
+    /// Create a valid field or method identifier, formatted as snake_case.
+    ///
+    /// With [FieldNaming::Original], keys that are valid identifiers are kept as they are.
+    /// Keywords are raw identifiers, except for keywords that cannot be raw,
+    /// which have a trailing underscore, e.g. `self_`.
+    fn to_module_ident(&self, naming: &Naming) -> syn::Ident;
+
+    /// Create the identifier of the module that the inner types of a type are defined in.
+    ///
+    /// Modules are snake_case with any field naming, so they cannot clash with type names,
+    /// e.g. `APIGateway` defines the type `APIGateway` and the module `apigateway`.
+    fn to_type_module_ident(&self) -> syn::Ident {
+        self.to_module_ident(&Naming::default())
     }
 
-    /// Create a valid type identifier, formatted as PascalCase.
-    fn to_type_ident(&self) -> syn::Ident;
+    /// Create a valid enum variant identifier, formatted as PascalCase.
+    fn to_variant_ident(&self, naming: &Naming) -> syn::Ident;
 
-    // /// Create an array type identifier formatted as PascalCase.
-    // fn to_array_type_ident(&self) -> String {
-    //     format!("{}Item", self.to_type_ident())
-    // }
+    /// Create a valid type identifier, formatted as PascalCase with the type prefix and suffix.
+    fn to_type_ident(&self, naming: &Naming) -> syn::Ident {
+        affixed_type_ident(&self.to_variant_ident(naming).to_string(), naming)
+    }
+
+    /// Create a type identifier for items in a mixed array, formatted as PascalCase.
+    fn to_item_type_ident(&self, naming: &Naming) -> syn::Ident {
+        affixed_type_ident(&format!("{}Item", self.to_variant_ident(naming)), naming)
+    }
 }
 
 impl<T> ConstIdentDef for T
@@ -59,44 +75,61 @@ where
     T: AsRef<str>,
 {
     fn to_variable_ident(&self) -> syn::Ident {
-        let self_ref = self.as_ref();
-
-        let inter = self_ref.replace(REPLACE_CHARS, "_");
-
-        let inter = inter
+        let inter = ident_base(self.as_ref())
             .split('_')
             .map(|item| item.to_uppercase())
             .collect::<Vec<_>>()
             .join("_");
 
-        let inter = match inter.starts_with(char::is_numeric) {
-            true => format!("_{}", inter),
-            false => inter,
+        let inter = prefix_non_start(inter);
+
+        match is_ident(&inter) {
+            true => syn::Ident::new(&inter, Span::call_site()),
+            false => syn::Ident::new(
+                &hex_ident("KEY", self.as_ref()).to_uppercase(),
+                Span::call_site(),
+            ),
+        }
+    }
+
+    fn to_module_ident(&self, naming: &Naming) -> syn::Ident {
+        let ident = match naming.fields {
+            FieldNaming::Original if is_ident(self.as_ref()) => self.as_ref().to_string(),
+            _ => self.to_variable_ident().to_string().to_lowercase(),
         };
 
-        syn::Ident::new(&inter, Span::call_site())
+        if NON_RAW_KEYWORDS.contains(&ident.as_str()) {
+            syn::Ident::new(&format!("{}_", ident), Span::call_site())
+        } else if is_ident(&ident) {
+            syn::Ident::new_raw(&ident, Span::call_site())
+        } else {
+            // lowercase characters that cannot be used in identifiers
+            syn::Ident::new(&hex_ident("key", &ident), Span::call_site())
+        }
     }
 
-    fn to_type_ident(&self) -> syn::Ident {
-        let inter = self.as_ref().replace(REPLACE_CHARS, "_");
-
-        let inter = match inter.contains("_") {
-            true => inter
-                .split('_')
-                .map(|item| {
-                    let mut chars = item.chars();
-
-                    match chars.next() {
-                        Some(c) => {
-                            let first_char = c.to_ascii_uppercase();
-                            let rest = chars.collect::<String>().to_ascii_lowercase();
-                            format!("{}{}", first_char, rest)
-                        }
-                        None => String::new(),
-                    }
-                })
-                .collect::<String>(),
-            false => {
+    fn to_variant_ident(&self, naming: &Naming) -> syn::Ident {
+        let inter = ident_base(self.as_ref());
+
+        let capitalize = |item: &str| {
+            let mut chars = item.chars();
+
+            match chars.next() {
+                Some(c) => {
+                    let first_char = c.to_uppercase().collect::<String>();
+                    let rest = chars.collect::<String>().to_lowercase();
+                    format!("{}{}", first_char, rest)
+                }
+                None => String::new(),
+            }
+        };
+
+        let inter = match (naming.types, inter.contains("_")) {
+            (TypeNaming::UpperCamelCase, _) => {
+                split_words(&inter).iter().map(|w| capitalize(w)).collect()
+            }
+            (TypeNaming::PascalCase, true) => inter.split('_').map(capitalize).collect::<String>(),
+            (TypeNaming::PascalCase, false) => {
                 // split at a capital letter, but preserve the letter
                 let inter = inter.chars().fold(String::new(), |mut acc, c| {
                     if c.is_uppercase() && !acc.is_empty() {
@@ -106,35 +139,116 @@ where
                     acc
                 });
 
-                inter
-                    .split("_")
-                    .map(|item| {
-                        let mut chars = item.chars();
-
-                        match chars.next() {
-                            Some(c) => {
-                                let first_char = c.to_ascii_uppercase();
-                                let rest = chars.collect::<String>().to_ascii_lowercase();
-                                format!("{}{}", first_char, rest)
-                            }
-                            None => String::new(),
-                        }
-                    })
-                    .collect::<String>()
-
-                // todo!()
+                inter.split("_").map(capitalize).collect::<String>()
             }
         };
 
-        let inter = match inter.starts_with(char::is_numeric) {
-            true => format!("_{}", inter),
-            false => inter,
+        let inter = match prefix_non_start(inter) {
+            // `Self` cannot be a type name, or a raw identifier
+            inter if inter == "Self" => "Self_".to_string(),
+            inter => inter,
+        };
+
+        match is_ident(&inter) {
+            true => syn::Ident::new(&inter, Span::call_site()),
+            false => hex_ident("key", self.as_ref()).to_variant_ident(naming),
+        }
+    }
+}
+
+/// Add the type prefix and suffix to a type name.
+fn affixed_type_ident(name: &str, naming: &Naming) -> syn::Ident {
+    let ident = format!("{}{}{}", naming.type_prefix, name, naming.type_suffix);
+
+    syn::Ident::new(&ident, Span::call_site())
+}
+
+/// Split a name into words at `_` and case changes, keeping acronyms together,
+/// e.g. `HTTPServer` is `HTTP` and `Server`.
+fn split_words(name: &str) -> Vec<String> {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, c) in chars.iter().copied().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let boundary = match i.checked_sub(1).map(|p| chars[p]) {
+            Some(prev) if c.is_uppercase() => {
+                prev.is_lowercase()
+                    || prev.is_numeric()
+                    || (prev.is_uppercase()
+                        && matches!(chars.get(i + 1), Some(n) if n.is_lowercase()))
+            }
+            _ => false,
         };
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
 
-        syn::Ident::new(&inter, Span::call_site())
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Replace characters that cannot be used in identifiers with `_`.
+///
+/// Keys without any characters that can be used, e.g. `""` or `"+"`,
+/// are encoded as hex instead, e.g. `key_2b`.
+fn ident_base(key: &str) -> String {
+    let replaced = key
+        .chars()
+        .map(|c| match unicode_ident::is_xid_continue(c) {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+
+    match replaced.chars().any(|c| c != '_') {
+        true => replaced,
+        false => hex_ident("key", key),
+    }
+}
+
+/// Prefix identifiers that do not start with a valid character, e.g. a digit, with `_`.
+fn prefix_non_start(ident: String) -> String {
+    match ident.chars().next() {
+        Some(c) if !unicode_ident::is_xid_start(c) && c != '_' => format!("_{}", ident),
+        _ => ident,
+    }
+}
+
+/// Returns true if a string is a valid identifier.
+fn is_ident(ident: &str) -> bool {
+    let mut chars = ident.chars();
+
+    match chars.next() {
+        Some(c) if unicode_ident::is_xid_start(c) || c == '_' => {
+            ident != "_" && chars.all(unicode_ident::is_xid_continue)
+        }
+        _ => false,
     }
 }
 
+/// An identifier from the hex encoding of a key, e.g. `key_2b` for `"+"`.
+fn hex_ident(prefix: &str, key: &str) -> String {
+    let hex = key
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    format!("{}_{}", prefix, hex)
+}
+
 impl Instantiate for toml::Value {
     fn instantiate(
         &self,
@@ -142,20 +256,144 @@ impl Instantiate for toml::Value {
         toml_value: &TomlValue,
         parents: Vec<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> proc_macro2::TokenStream {
         use toml::Value::*;
 
+        // values in mixed arrays are wrapped in their enum variant
+        if let TomlValue::Union(_) = toml_value {
+            let member = toml_value
+                .union_member(self)
+                .expect("value must be a member of the union");
+
+            let enum_ty = qualified_type(&parents, &key.to_item_type_ident(naming));
+
+            let variant = MemberKind::of(member).variant_ident();
+            let inner = self.instantiate(
+                &union_member_key(key, member),
+                member,
+                parents,
+                runtime_path,
+                naming,
+            );
+
+            return quote! { #enum_ty :: #variant(#inner) };
+        }
+
+        // tables in tagged arrays are wrapped in the variant selected by their tag
+        if let TomlValue::Tagged { tag, variants } = toml_value {
+            let variant_name = self
+                .get(tag)
+                .and_then(|t| t.as_str())
+                .expect("tag must be a string");
+            let variant = variants
+                .get(variant_name)
+                .expect("tag must select a variant");
+
+            let enum_ty = qualified_type(&parents, &key.to_type_ident(naming));
+            let enum_mod = key.to_type_module_ident();
+
+            let mut parents = parents.clone();
+            parents.push(&enum_mod);
+
+            let variant_ident = variant_name.to_variant_ident(naming);
+            let inner = self.instantiate(variant_name, variant, parents, runtime_path, naming);
+
+            return quote! { #enum_ty :: #variant_ident(#inner) };
+        }
+
+        // tables mapped to user-defined types use their struct literal or constructor
+        if let TomlValue::Mapped { target, table } = toml_value {
+            let (Table(map), TomlValue::Table(tab)) = (self, table.as_ref()) else {
+                unimplemented!("mapped values are tables")
+            };
+
+            let self_mod = key.to_type_module_ident();
+            let mut parents = parents.clone();
+            parents.push(&self_mod);
+
+            let value = |k: &std::string::String| {
+                let val = tab.get(k).expect("key should exist in schema");
+                map.get(k).expect("key should exist in table").instantiate(
+                    k,
+                    val,
+                    parents.clone(),
+                    runtime_path,
+                    naming,
+                )
+            };
+            let field = |k: &std::string::String| {
+                let ident = k.to_module_ident(naming);
+                let val = value(k);
+                quote! { #ident: #val }
+            };
+
+            let ty = target.ty();
+            return match target.init() {
+                MapInit::Literal => {
+                    let fields = tab.keys().map(field);
+                    quote! { #ty { #(#fields),* } }
+                }
+                MapInit::Fields(names) => {
+                    let fields = names.iter().map(field);
+                    quote! { #ty { #(#fields),* } }
+                }
+                MapInit::Constructor { args, .. } => {
+                    let func = target.init().func().expect("constructor has a function");
+                    let args = args.iter().map(value);
+                    quote! { #func(#(#args),*) }
+                }
+            };
+        }
+
+        // the index of an array is instantiated by its parent table
+        if let TomlValue::Indexed { array, .. } = toml_value {
+            return self.instantiate(key, array, parents, runtime_path, naming);
+        }
+
+        // strings with known values are enum variants
+        if let (TomlValue::Enum(_), String(val)) = (toml_value, self) {
+            let enum_ty = qualified_type(&parents, &key.to_type_ident(naming));
+            let variant = val.to_variant_ident(naming);
+
+            return quote! { #enum_ty :: #variant };
+        }
+
+        // arrays of flags are the union of their flags
+        if let (TomlValue::Flags(_), Array(values)) = (toml_value, self) {
+            let flags_ty = qualified_type(&parents, &key.to_type_ident(naming));
+            let flags = values
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(|flag| flag.to_variable_ident());
+
+            return quote! { #flags_ty::empty()#(.union(#flags_ty::#flags))* };
+        }
+
+        // values are checked when converted, except for defaults of missing keys
+        if let TomlValue::Typed(value_type) = toml_value {
+            return match value_type.instantiate(key, self, runtime_path) {
+                Ok(tokens) => tokens,
+                Err(msg) => {
+                    let msg = format!("{}: {}", key, msg);
+                    quote! { compile_error!(#msg) }
+                }
+            };
+        }
+
         match self {
             // cases when items are instantiated as fields in an array
             String(val) => quote! { #val },
             Integer(val) => quote! { #val },
-            Float(val) => quote! { #val },
+            Float(val) => non_finite_float(*val, quote! { f64 }).unwrap_or_else(|| quote! { #val }),
             Boolean(val) => quote! { #val },
 
             // items with inner impls
-            Datetime(datetime) => datetime.instantiate(key, toml_value, vec![], runtime_path),
-            Array(values) => values.instantiate(key, toml_value, parents, runtime_path),
-            Table(map) => map.instantiate(key, toml_value, parents, runtime_path),
+            Datetime(datetime) => {
+                datetime.instantiate(key, toml_value, vec![], runtime_path, naming)
+            }
+            Array(values) => values.instantiate(key, toml_value, parents, runtime_path, naming),
+            Table(map) => map.instantiate(key, toml_value, parents, runtime_path, naming),
         }
     }
 }
@@ -167,48 +405,75 @@ impl Instantiate for toml::Table {
         toml_value: &TomlValue,
         parents: Vec<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> proc_macro2::TokenStream {
-        let table_type = key.to_type_ident();
-        let table_mod = key.to_module_ident();
-
-        let table_ty = match parents.len() {
-            0 => {
-                quote! { #table_type }
-            }
-            _ => {
-                let p = parents.iter().collect::<Punctuated<_, syn::Token![::]>>();
-                quote! { #p :: #table_type }
-            }
-        };
+        let table_ty = qualified_type(&parents, &key.to_type_ident(naming));
+        let table_mod = key.to_type_module_ident();
 
         let mut parents = parents.clone();
         parents.push(&table_mod);
 
         let new_params = match toml_value {
-            TomlValue::Table(tab) => tab
-                .iter()
-                .map(|(key, val)| {
-                    let inner_val = self.get(key).expect("key should exist in table");
-
-                    inner_val.instantiate(key, val, parents.clone(), runtime_path)
-                })
-                .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>(),
-            TomlValue::TableMap {
-                keys,
-                first,
-                value_type,
-            } => {
-                let map_vals = keys
+            TomlValue::Table(tab) => {
+                let indexes = tab.iter().filter_map(|(key, val)| match val {
+                    TomlValue::Indexed { key: by, array } => {
+                        let values = self.get(key).and_then(|v| v.as_array());
+                        let values = values.expect("indexed key should be an array");
+
+                        Some(index_value(
+                            key,
+                            by,
+                            values,
+                            array,
+                            parents.clone(),
+                            runtime_path,
+                            naming,
+                        ))
+                    }
+                    _ => None,
+                });
+
+                tab.iter()
+                    .map(|(key, val)| {
+                        let inner_val = self.get(key).expect("key should exist in table");
+
+                        inner_val.instantiate(key, val, parents.clone(), runtime_path, naming)
+                    })
+                    .chain(indexes)
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>()
+            }
+            TomlValue::TableMap { keys, value_type } => {
+                let value_key = map_value_key(key, keys);
+
+                let entries = keys
                     .iter()
                     .map(|k| {
                         let key_lit = syn::LitStr::new(k, Span::call_site());
 
                         let value = self.get(k).expect("key should exist in table");
-                        let value =
-                            value.instantiate(first, value_type, parents.clone(), runtime_path);
-
-                        quote! {#key_lit => #value}
+                        let value = value.instantiate(
+                            &value_key,
+                            value_type,
+                            parents.clone(),
+                            runtime_path,
+                            naming,
+                        );
+
+                        (k, key_lit, value)
                     })
+                    .collect::<Vec<_>>();
+
+                let map_vals = entries
+                    .iter()
+                    .map(|(_, key_lit, value)| quote! {#key_lit => #value})
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
+
+                // keys are sorted by their bytes, for the binary search of `ConstMap::get`
+                let mut sorted = entries.iter().collect::<Vec<_>>();
+                sorted.sort_by(|(a, ..), (b, ..)| a.as_bytes().cmp(b.as_bytes()));
+                let sorted_vals = sorted
+                    .iter()
+                    .map(|(_, key_lit, value)| quote! {(#key_lit, #value)})
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
                 let map_value = quote! {{
@@ -217,12 +482,25 @@ impl Instantiate for toml::Table {
                         #map_vals
                     }
                 }};
+                let sorted_value = quote! {
+                    #runtime_path::ConstMap::new(&[#sorted_vals])
+                };
+                let private_values = match cfg!(feature = "phf") {
+                    true => vec![map_value, sorted_value],
+                    false => vec![sorted_value],
+                };
 
                 self.iter()
                     .map(|(_, f_val)| {
-                        f_val.instantiate(first, value_type, parents.clone(), runtime_path)
+                        f_val.instantiate(
+                            &value_key,
+                            value_type,
+                            parents.clone(),
+                            runtime_path,
+                            naming,
+                        )
                     })
-                    .chain([map_value])
+                    .chain(private_values)
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>()
             }
             _ => unimplemented!("expected a table or table map"),
@@ -243,11 +521,49 @@ impl Instantiate for toml::value::Array {
         toml_value: &TomlValue,
         parents: Vec<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> proc_macro2::TokenStream {
-        let arr = if let TomlValue::Array(arr) = toml_value {
-            arr
-        } else {
-            unimplemented!("expected a toml array value");
+        let arr = match toml_value {
+            TomlValue::Array(arr) => arr,
+            TomlValue::FixedArray { element, .. } => {
+                let elements = match element {
+                    Some(val) => self
+                        .iter()
+                        .map(|elem| {
+                            elem.instantiate(key, val, parents.clone(), runtime_path, naming)
+                        })
+                        .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>(),
+                    None => Punctuated::new(),
+                };
+
+                return quote! {
+                    [ #elements ]
+                };
+            }
+            TomlValue::Tuple(values) => {
+                let elements = self
+                    .iter()
+                    .zip(values)
+                    .enumerate()
+                    .map(|(i, (elem, val))| {
+                        let element_key = tuple_element_key(key, i);
+                        let element = elem.instantiate(
+                            &element_key,
+                            val,
+                            parents.clone(),
+                            runtime_path,
+                            naming,
+                        );
+
+                        quote! { #element, }
+                    })
+                    .collect::<pm2::TokenStream>();
+
+                return quote! {
+                    ( #elements )
+                };
+            }
+            _ => unimplemented!("expected a toml array value"),
         };
 
         let val = match arr.first() {
@@ -257,7 +573,7 @@ impl Instantiate for toml::value::Array {
 
         let elements = self
             .iter()
-            .map(|elem| elem.instantiate(key, val, parents.clone(), runtime_path))
+            .map(|elem| elem.instantiate(key, val, parents.clone(), runtime_path, naming))
             .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
         quote! {
@@ -266,6 +582,56 @@ impl Instantiate for toml::value::Array {
     }
 }
 
+/// Elements of an indexed array in a `ConstMap`, sorted by the value of their index key.
+fn index_value(
+    key: &str,
+    by: &str,
+    values: &[toml::Value],
+    array: &TomlValue,
+    parents: Vec<&Ident>,
+    runtime_path: &syn::Path,
+    naming: &Naming,
+) -> pm2::TokenStream {
+    let element = match array {
+        TomlValue::Array(arr) => arr.first(),
+        TomlValue::FixedArray { element, .. } => element.as_deref(),
+        _ => None,
+    };
+
+    let mut entries = match element {
+        Some(element) => values
+            .iter()
+            .map(|value| {
+                let index_key = value
+                    .get(by)
+                    .and_then(|k| k.as_str())
+                    .expect("index keys are checked when converted");
+                let value = value.instantiate(key, element, parents.clone(), runtime_path, naming);
+
+                (index_key, value)
+            })
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+    entries.sort_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+    let entries = entries.iter().map(|(k, v)| quote! { (#k, #v) });
+    quote! {
+        #runtime_path::ConstMap::new(&[#(#entries),*])
+    }
+}
+
+/// Path to a generated type, relative to the module of the root definition.
+fn qualified_type(parents: &[&Ident], type_ident: &Ident) -> pm2::TokenStream {
+    match parents.len() {
+        0 => quote! { #type_ident },
+        _ => {
+            let p = parents.iter().collect::<Punctuated<_, syn::Token![::]>>();
+            quote! { #p :: #type_ident }
+        }
+    }
+}
+
 // datetime structs do not require a key, as they are already defined.
 impl Instantiate for toml::value::Datetime {
     fn instantiate(
@@ -274,12 +640,13 @@ impl Instantiate for toml::value::Datetime {
         _: &TomlValue,
         _: Vec<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> proc_macro2::TokenStream {
         match (self.date, self.time, self.offset) {
             (Some(d), Some(t), Some(o)) => {
-                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
-                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
-                let o = o.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
+                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
+                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
+                let o = o.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
 
                 quote! {
                     #runtime_path::OffsetDateTime {
@@ -290,8 +657,8 @@ impl Instantiate for toml::value::Datetime {
                 }
             }
             (Some(d), Some(t), None) => {
-                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
-                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
+                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
+                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
 
                 quote! {
                     #runtime_path::LocalDateTime {
@@ -301,7 +668,7 @@ impl Instantiate for toml::value::Datetime {
                 }
             }
             (Some(d), None, None) => {
-                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
+                let d = d.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
 
                 quote! {
                     #runtime_path::LocalDate {
@@ -310,7 +677,7 @@ impl Instantiate for toml::value::Datetime {
                 }
             }
             (None, Some(t), None) => {
-                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path);
+                let t = t.instantiate(k, &TomlValue::Boolean, vec![], runtime_path, naming);
 
                 quote! {
                     #runtime_path::LocalTime {
@@ -332,6 +699,7 @@ impl Instantiate for toml::value::Date {
         _: &TomlValue,
         _: Vec<&Ident>,
         runtime_path: &syn::Path,
+        _: &Naming,
     ) -> proc_macro2::TokenStream {
         let year = self.year;
         let month = self.month;
@@ -354,6 +722,7 @@ impl Instantiate for toml::value::Time {
         _: &TomlValue,
         _: Vec<&Ident>,
         runtime_path: &syn::Path,
+        _: &Naming,
     ) -> proc_macro2::TokenStream {
         let hour = self.hour;
         let minute = self.minute;
@@ -378,6 +747,7 @@ impl Instantiate for toml::value::Offset {
         _: &TomlValue,
         _: Vec<&Ident>,
         runtime_path: &syn::Path,
+        _: &Naming,
     ) -> proc_macro2::TokenStream {
         match self {
             toml::value::Offset::Z => quote! { #runtime_path::Offset::Z },
@@ -404,8 +774,13 @@ mod tests {
 
         let root_ident = Ident::new("ROOT_TABLE", Span::call_site());
         let runtime_path = syn::parse_str("toml_const").expect("valid path");
-        let instantiation =
-            toml.instantiate(&root_ident.to_string(), &value, vec![], &runtime_path);
+        let instantiation = toml.instantiate(
+            &root_ident.to_string(),
+            &value,
+            vec![],
+            &runtime_path,
+            &Naming::default(),
+        );
 
         println!("Table instantiation: {}", instantiation);
     }
@@ -441,7 +816,7 @@ mod tests {
     fn test_cfg_identifiers() {
         let key = "cfg(any(target_os = \"android\", target_os = \"ios\"))";
         let var = key.to_variable_ident();
-        let ty = key.to_type_ident();
+        let ty = key.to_type_ident(&Naming::default());
         assert_eq!(
             var.to_string(),
             "CFG_ANY_TARGET_OS____ANDROID___TARGET_OS____IOS___"
@@ -450,8 +825,75 @@ mod tests {
 
         let simple_key = "cfg(unix)";
         let var_simple = simple_key.to_variable_ident();
-        let ty_simple = simple_key.to_type_ident();
+        let ty_simple = simple_key.to_type_ident(&Naming::default());
         assert_eq!(var_simple.to_string(), "CFG_UNIX_");
         assert_eq!(ty_simple.to_string(), "CfgUnix");
     }
+
+    #[test]
+    fn test_arbitrary_key_identifiers() {
+        let idents = |key: &str| {
+            [
+                key.to_variable_ident().to_string(),
+                key.to_module_ident(&Naming::default()).to_string(),
+                key.to_type_ident(&Naming::default()).to_string(),
+            ]
+        };
+
+        assert_eq!(idents(""), ["KEY_", "r#key_", "Key"]);
+        assert_eq!(idents("+"), ["KEY_2B", "r#key_2b", "Key2b"]);
+        assert_eq!(idents("café"), ["CAFÉ", "r#café", "Café"]);
+        assert_eq!(idents("self"), ["SELF", "self_", "Self_"]);
+        assert_eq!(idents("crate"), ["CRATE", "crate_", "Crate"]);
+        assert_eq!(idents("type"), ["TYPE", "r#type", "Type"]);
+        assert_eq!(idents("1st"), ["_1ST", "r#_1st", "_1st"]);
+    }
+
+    #[test]
+    fn test_naming_conventions() {
+        let naming = Naming {
+            fields: FieldNaming::Original,
+            types: TypeNaming::UpperCamelCase,
+            type_prefix: "Config".to_string(),
+            type_suffix: String::new(),
+        };
+
+        assert_eq!(
+            "HTTPServer".to_type_ident(&naming).to_string(),
+            "ConfigHttpServer"
+        );
+        assert_eq!(
+            "HTTPServer".to_variant_ident(&naming).to_string(),
+            "HttpServer"
+        );
+        assert_eq!(
+            "io_ports".to_item_type_ident(&naming).to_string(),
+            "ConfigIoPortsItem"
+        );
+        assert_eq!(
+            "HTTPServer".to_module_ident(&naming).to_string(),
+            "r#HTTPServer"
+        );
+        assert_eq!(
+            "log-level".to_module_ident(&naming).to_string(),
+            "r#log_level"
+        );
+        assert_eq!("Self".to_module_ident(&naming).to_string(), "Self_");
+
+        // default conventions
+        let default = Naming::default();
+        assert_eq!(
+            "HTTPServer".to_type_ident(&default).to_string(),
+            "HTTPServer"
+        );
+        assert_eq!(
+            "HTTPServer".to_module_ident(&default).to_string(),
+            "r#httpserver"
+        );
+
+        assert_eq!(
+            split_words("myHTTPServer_v2Api"),
+            ["my", "HTTP", "Server", "v2", "Api"]
+        );
+    }
 }
@@ -1,7 +1,10 @@
 mod check;
+mod derive;
 mod instantiate;
 mod normalize;
+mod options;
 mod parse;
+mod types;
 
 use std::path::PathBuf;
 
@@ -15,14 +18,38 @@ use syn::{parse::Parse, parse_macro_input, Token};
 
 use crate::{instantiate::ConstIdentDef, normalize::TomlValue};
 
-/// Private map field for tables that can be represented as hashmaps
-const MAP_FIELD: &str = "__map__";
+/// Private map field for tables that can be represented as hashmaps.
+///
+/// Keys only generate uppercase fields with `#[naming(fields = original)]`,
+/// which reports keys that generate a private field as collisions.
+const MAP_FIELD: &str = "MAP";
+
+/// Private field for the sorted entries of table maps, which do not need hashing.
+const SORTED_FIELD: &str = "SORTED";
 
 struct MacroInvocation<T> {
     runtime_path: syn::Path,
     input: T,
 }
 
+/// Input to [__toml_const_inner], with the directory that paths were resolved from.
+struct InnerInput {
+    base_path: PathBuf,
+    input: MacroInput,
+}
+
+impl Parse for InnerInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let base_path: syn::LitStr = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        Ok(Self {
+            base_path: PathBuf::from(base_path.value()),
+            input: input.parse()?,
+        })
+    }
+}
+
 impl<T: Parse> Parse for MacroInvocation<T> {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let runtime_path = input.parse()?;
@@ -58,6 +85,7 @@ pub fn __toml_const(input: pm::TokenStream) -> pm::TokenStream {
         .map(|i| i.to_const_defs(&abs_manifest_path))
         .collect::<pm2::TokenStream>();
 
+    let base_path = abs_manifest_path.to_string_lossy().to_string();
     let inner_calls = input
         .0
         .iter()
@@ -65,7 +93,7 @@ pub fn __toml_const(input: pm::TokenStream) -> pm::TokenStream {
             let absolute = i.to_abs_path(&abs_manifest_path);
             quote! {
                 #runtime_path::__toml_const_inner! {
-                    #runtime_path; #absolute
+                    #runtime_path; #base_path; #absolute
                 }
             }
         })
@@ -104,6 +132,7 @@ pub fn __toml_const_ws(input: pm::TokenStream) -> pm::TokenStream {
         .collect::<pm2::TokenStream>();
 
     // let collected = input.to_const_defs(&abs_ws_path);
+    let base_path = abs_ws_path.to_string_lossy().to_string();
     let inner_calls = input
         .0
         .iter()
@@ -111,7 +140,7 @@ pub fn __toml_const_ws(input: pm::TokenStream) -> pm::TokenStream {
             let absolute = i.to_abs_path(&abs_ws_path);
             quote! {
                 #runtime_path::__toml_const_inner! {
-                    #runtime_path; #absolute
+                    #runtime_path; #base_path; #absolute
                 }
             }
         })
@@ -125,6 +154,17 @@ pub fn __toml_const_ws(input: pm::TokenStream) -> pm::TokenStream {
     .into()
 }
 
+/// Instantiate a struct from a TOML file, using the struct as the schema.
+///
+/// See the crate documentation for details.
+#[proc_macro_derive(TomlConst, attributes(toml_const))]
+pub fn toml_const_derive(input: pm::TokenStream) -> pm::TokenStream {
+    match derive::derive_toml_const(parse_macro_input!(input)) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
 /// TODO: working title
 #[proc_macro_attribute]
 pub fn unwrap_datetime(_attr: pm::TokenStream, item: pm::TokenStream) -> pm::TokenStream {
@@ -151,22 +191,28 @@ pub fn unwrap_datetime(_attr: pm::TokenStream, item: pm::TokenStream) -> pm::Tok
 pub fn __toml_const_inner(input: pm::TokenStream) -> pm::TokenStream {
     let MacroInvocation {
         runtime_path,
-        input,
-    }: MacroInvocation<MacroInput> = parse_macro_input!(input);
+        input: InnerInput { base_path, input },
+    }: MacroInvocation<InnerInput> = parse_macro_input!(input);
 
-    let toml_table = match input.generate_toml_table() {
+    let options = match input.options() {
+        Ok(opts) => opts,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let naming = &options.naming;
+
+    let toml_table = match input.generate_toml_table(&options, &base_path) {
         Ok(tt) => tt,
         Err(e) => return e.into(),
     };
 
-    match check::check_unauthorized_keys(&toml_table) {
-        Ok(_) => (),
-        Err(e) => return e.into(),
-    }
+    let field_checks = TomlValue::field_checks(&toml_table, &options, &runtime_path);
 
     // perform normalization
-    let toml_val_table = TomlValue::from(toml_table.clone());
-    let toml_val_table = match toml_val_table.normalize() {
+    let toml_val_table = match TomlValue::from_table(&toml_table, &options) {
+        Ok(t) => t,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let toml_val_table = match toml_val_table.normalize(&options) {
         Ok(n) => n,
         Err(e) => {
             return syn::Error::new(Span::call_site(), e.to_string())
@@ -174,7 +220,14 @@ pub fn __toml_const_inner(input: pm::TokenStream) -> pm::TokenStream {
                 .into()
         }
     };
-    let toml_val_table = toml_val_table.reduce();
+    let toml_val_table = match toml_val_table.reduce(&options) {
+        Ok(r) => r,
+        Err(e) => {
+            return syn::Error::new(Span::call_site(), e.to_string())
+                .to_compile_error()
+                .into()
+        }
+    };
 
     let mut toml_table_val = toml::Value::Table(toml_table);
     toml_val_table.normalize_toml(&mut toml_table_val);
@@ -187,24 +240,30 @@ pub fn __toml_const_inner(input: pm::TokenStream) -> pm::TokenStream {
         Err(e) => return e.to_compile_error().into(),
     };
 
-    let table_definitions = toml_val_table.definition(
-        &input.item_ident.to_string(),
-        &definition_attrs,
-        &runtime_path,
-    );
+    let table_definitions = match options.shared_types {
+        true => toml_val_table.shared_definition(
+            &input.item_ident.to_string(),
+            &definition_attrs,
+            &runtime_path,
+            naming,
+        ),
+        false => toml_val_table.definition(
+            &input.item_ident.to_string(),
+            &definition_attrs,
+            &runtime_path,
+            naming,
+        ),
+    };
 
     let instantiation = toml_table.instantiate(
         &input.item_ident.to_string(),
         &toml_val_table,
-        vec![],
+        options.namespace.iter().collect(),
         &runtime_path,
+        naming,
     );
 
-    let pub_token = if input.is_pub {
-        quote! {pub}
-    } else {
-        quote! {}
-    };
+    let vis = &input.vis;
 
     let static_const_token = match input.static_const {
         true => quote! {const},
@@ -212,7 +271,7 @@ pub fn __toml_const_inner(input: pm::TokenStream) -> pm::TokenStream {
     };
 
     let item_ident = &input.item_ident;
-    let item_ty = input.item_ident.to_string().to_type_ident();
+    let item_ty = input.item_ident.to_string().to_type_ident(naming);
 
     let instance_attrs = match input.instantiation_attrs() {
         Ok(instance) => instance
@@ -222,11 +281,28 @@ pub fn __toml_const_inner(input: pm::TokenStream) -> pm::TokenStream {
         Err(e) => return e.to_compile_error().into(),
     };
 
+    // the namespace imports the caller's items, so that mapped types resolve as they would outside
+    let table_definitions = match &options.namespace {
+        Some(namespace) => quote! {
+            #vis mod #namespace {
+                #[allow(unused_imports)]
+                use super::*;
+
+                #table_definitions
+            }
+
+            #vis use #namespace::#item_ty;
+        },
+        None => table_definitions,
+    };
+
     quote! {
+        #field_checks
+
         #table_definitions
 
         #instance_attrs
-        #pub_token #static_const_token #item_ident: #item_ty = #instantiation;
+        #vis #static_const_token #item_ident: #item_ty = #instantiation;
     }
     .into()
 }
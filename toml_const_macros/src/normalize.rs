@@ -12,14 +12,22 @@
 //! - arrays are empty
 //! - dates are set to `1970-01-01T00:00:00Z`
 
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
 use indexmap::IndexMap;
 use proc_macro2 as pm2;
 use proc_macro2::Span;
 use quote::{quote, ToTokens};
-use syn::{punctuated::Punctuated, Ident};
+use syn::{ext::IdentExt, punctuated::Punctuated, Ident};
 use toml::value::{Date, Datetime};
 
-use crate::{instantiate::ConstIdentDef, MAP_FIELD};
+use crate::{
+    instantiate::ConstIdentDef,
+    options::{IndexedArray, MacroOptions, MapTarget, MappedTable, Naming, TaggedArray, ValueSet},
+    types::ValueType,
+    MAP_FIELD, SORTED_FIELD,
+};
 
 const DEFAULT_DATE: Date = Date {
     year: 1970,
@@ -34,6 +42,12 @@ const DEFAULT_TIME: toml::value::Time = toml::value::Time {
 };
 const DEFAULT_OFFSET: toml::value::Offset = toml::value::Offset::Z;
 
+/// Flags are backed by a `u32`.
+const MAX_FLAGS: usize = 32;
+
+/// Suggestion for keys that generate the same identifier.
+const COLLISION_HINT: &str = "rename one of them with #[rename] or use #[suffix_collisions]";
+
 #[derive(Clone, Debug)]
 pub enum NormalizationError {
     /// A mismatch in value types.
@@ -64,12 +78,188 @@ pub enum TomlValue {
     Table(IndexMap<String, TomlValue>),
 
     /// A table map is a subset of a table that contains identical values for all keys.
+    ///
+    /// Table and array types in the value are named by [map_value_key].
     TableMap {
         keys: Vec<String>,
-        /// The key that table and array types inherit
-        first: String,
         value_type: Box<TomlValue>,
     },
+
+    /// Distinct value types found in the same array.
+    ///
+    /// Contains at most one member of each [MemberKind], ordered by kind.
+    Union(Vec<TomlValue>),
+
+    /// Tables in an array that are split into variants by the value of a tag key.
+    ///
+    /// Variant tables do not contain the tag key.
+    Tagged {
+        tag: String,
+        variants: IndexMap<String, TomlValue>,
+    },
+
+    /// An array with the same length everywhere, generated as `[T; N]`.
+    ///
+    /// Empty arrays do not have an element type.
+    FixedArray {
+        len: usize,
+        element: Option<Box<TomlValue>>,
+    },
+
+    /// A fixed-size array with distinct element types, generated as a tuple.
+    Tuple(Vec<TomlValue>),
+
+    /// A value generated as another type, e.g. `u16` instead of `i64`.
+    Typed(ValueType),
+
+    /// Strings generated as an enum, one variant per value.
+    Enum(Vec<String>),
+
+    /// An array of strings generated as bitflags, one flag per value.
+    Flags(Vec<String>),
+
+    /// A table instantiated as a user-defined type, instead of a generated struct.
+    Mapped {
+        target: MapTarget,
+        table: Box<TomlValue>,
+    },
+
+    /// An array of tables that its parent table also indexes by the string value of a key.
+    ///
+    /// Table maps are not reduced from tables that contain indexed arrays.
+    Indexed {
+        key: String,
+        array: Box<TomlValue>,
+    },
+}
+
+/// Types that are defined once for each distinct shape, with `#[shared_types]`.
+#[derive(Default)]
+struct SharedTypes {
+    enabled: bool,
+    /// Modules that definitions are currently in, relative to the macro input
+    parents: Vec<Ident>,
+    /// First definition of each shape
+    shapes: HashMap<Shape, SharedType>,
+}
+
+/// A value that shared types are defined for, with the names that its definition derives
+/// from its key and that an alias cannot rename.
+#[derive(PartialEq, Eq, Hash)]
+struct Shape {
+    value: TomlValue,
+    /// Value type of a table map with several keys, which is named after the map,
+    /// e.g. `ServersValue`
+    value_key: Option<String>,
+}
+
+/// Location of the first definition of a shape.
+struct SharedType {
+    parents: Vec<Ident>,
+    ty: Ident,
+    /// Module that contains the types inside the shape, if any
+    module: Option<Ident>,
+    /// Key enum of a table map
+    key_enum: Option<Ident>,
+}
+
+impl SharedTypes {
+    /// Define the types inside a module.
+    fn in_module<T>(&mut self, module: &Ident, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.parents.push(module.clone());
+        let res = f(self);
+        self.parents.pop();
+
+        res
+    }
+
+    /// Returns aliases of the first definition, if a shape was already defined.
+    ///
+    /// Otherwise, the shape is recorded and defined as usual.
+    /// Unions are not shared, as their members are defined next to them instead of in a module.
+    fn alias(&mut self, value: &TomlValue, key: &str, naming: &Naming) -> Option<pm2::TokenStream> {
+        let has_module = match value {
+            TomlValue::Table(_) | TomlValue::TableMap { .. } | TomlValue::Tagged { .. } => true,
+            TomlValue::Enum(_) | TomlValue::Flags(_) => false,
+            _ => return None,
+        };
+        if !self.enabled {
+            return None;
+        }
+
+        let shape = Shape {
+            value: value.clone(),
+            value_key: match value {
+                TomlValue::TableMap { keys, .. } if keys.len() > 1 => {
+                    Some(map_value_key(key, keys))
+                }
+                _ => None,
+            },
+        };
+        let first = match self.shapes.get(&shape) {
+            Some(first) => first,
+            None => {
+                let first = SharedType {
+                    parents: self.parents.clone(),
+                    ty: key.to_type_ident(naming),
+                    module: has_module.then(|| key.to_type_module_ident()),
+                    key_enum: map_key_type(value, key, naming),
+                };
+                self.shapes.insert(shape, first);
+                return None;
+            }
+        };
+
+        // relative to the closest common module, which is always inside the macro input
+        let common = self
+            .parents
+            .iter()
+            .zip(&first.parents)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let prefix = match self.parents.len() - common {
+            0 => quote! { self:: },
+            up => {
+                let supers = (0..up).map(|_| quote! { super:: });
+                quote! { #(#supers)* }
+            }
+        };
+        let down = &first.parents[common..];
+
+        let self_ty = key.to_type_ident(naming);
+        let first_ty = &first.ty;
+        let module = first.module.as_ref().map(|first_mod| {
+            let self_mod = key.to_type_module_ident();
+            quote! { pub use #prefix #(#down::)* #first_mod as #self_mod; }
+        });
+        let key_enum = first
+            .key_enum
+            .as_ref()
+            .zip(map_key_type(value, key, naming));
+        let key_enum = key_enum.map(|(first_key, self_key)| {
+            quote! { pub type #self_key = #prefix #(#down::)* #first_key; }
+        });
+
+        Some(quote! {
+            pub type #self_ty = #prefix #(#down::)* #first_ty;
+            #module
+            #key_enum
+        })
+    }
+}
+
+/// Kinds of values that can be members of a [TomlValue::Union].
+///
+/// Each kind is generated as an enum variant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemberKind {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    Array,
+    Table,
 }
 
 impl std::error::Error for NormalizationError {
@@ -101,7 +291,15 @@ impl std::fmt::Display for NormalizationError {
                     f,
                     "Value mismatch at {} - found: {:?} and {:?}",
                     path, value_types.0, value_types.1
-                )
+                )?;
+
+                match value_types.as_ref() {
+                    (TomlValue::Integer, TomlValue::Float)
+                    | (TomlValue::Float, TomlValue::Integer) => {
+                        write!(f, " (use #[promote_numbers] to unify integers and floats)")
+                    }
+                    _ => Ok(()),
+                }
             }
         }
     }
@@ -114,6 +312,9 @@ impl From<TomlValue> for toml::Value {
             TomlValue::Integer => toml::Value::Integer(Default::default()),
             TomlValue::Float => toml::Value::Float(Default::default()),
             TomlValue::Boolean => toml::Value::Boolean(Default::default()),
+            TomlValue::Typed(value_type) => value_type.default_value(),
+            TomlValue::Enum(values) => toml::Value::String(values[0].clone()),
+            TomlValue::Flags(_) => toml::Value::Array(vec![]),
             TomlValue::Datetime { date, time, offset } => {
                 toml::Value::Datetime(toml::value::Datetime {
                     date: if date { Some(DEFAULT_DATE) } else { None },
@@ -124,16 +325,74 @@ impl From<TomlValue> for toml::Value {
             TomlValue::Array(elements) => {
                 toml::Value::Array(elements.into_iter().map(|e| e.into()).collect())
             }
+            TomlValue::FixedArray { len, element } => toml::Value::Array(match element {
+                Some(e) => vec![(*e).into(); len],
+                None => vec![],
+            }),
+            TomlValue::Tuple(elements) => {
+                toml::Value::Array(elements.into_iter().map(|e| e.into()).collect())
+            }
             TomlValue::Table(sub_table) => {
                 toml::Value::Table(sub_table.into_iter().map(|(k, v)| (k, v.into())).collect())
             }
-            TomlValue::TableMap {
-                keys, value_type, ..
-            } => toml::Value::Table(
+            TomlValue::TableMap { keys, value_type } => toml::Value::Table(
                 keys.into_iter()
                     .map(|k| (k, (*value_type.clone()).into()))
                     .collect(),
             ),
+            TomlValue::Mapped { table, .. } => (*table).into(),
+            TomlValue::Indexed { array, .. } => (*array).into(),
+            TomlValue::Union(members) => members
+                .into_iter()
+                .next()
+                .expect("unions contain at least 2 members")
+                .into(),
+            TomlValue::Tagged { tag, variants } => {
+                let (variant, variant_table) = variants
+                    .into_iter()
+                    .next()
+                    .expect("tagged tables contain at least 1 variant");
+
+                let mut table: toml::Value = variant_table.into();
+                if let toml::Value::Table(t) = &mut table {
+                    t.insert(tag, toml::Value::String(variant));
+                }
+
+                table
+            }
+        }
+    }
+}
+
+impl Eq for TomlValue {}
+
+// tables are equal regardless of their order, so their entries are hashed in key order
+impl Hash for TomlValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let hash_entries = |entries: &IndexMap<String, TomlValue>, state: &mut H| {
+            let mut entries = entries.iter().collect::<Vec<_>>();
+            entries.sort_by_key(|(k, _)| *k);
+            entries.hash(state);
+        };
+
+        std::mem::discriminant(self).hash(state);
+        match self {
+            TomlValue::String | TomlValue::Integer | TomlValue::Float | TomlValue::Boolean => (),
+            TomlValue::Datetime { date, time, offset } => (date, time, offset).hash(state),
+            TomlValue::Array(values) | TomlValue::Union(values) | TomlValue::Tuple(values) => {
+                values.hash(state)
+            }
+            TomlValue::Table(table) => hash_entries(table, state),
+            TomlValue::TableMap { keys, value_type } => (keys, value_type).hash(state),
+            TomlValue::Tagged { tag, variants } => {
+                tag.hash(state);
+                hash_entries(variants, state);
+            }
+            TomlValue::FixedArray { len, element } => (len, element).hash(state),
+            TomlValue::Typed(value_type) => value_type.hash(state),
+            TomlValue::Enum(values) | TomlValue::Flags(values) => values.hash(state),
+            TomlValue::Mapped { target, table } => (target, table).hash(state),
+            TomlValue::Indexed { key, array } => (key, array).hash(state),
         }
     }
 }
@@ -165,6 +424,430 @@ impl From<toml::Table> for TomlValue {
 }
 
 impl TomlValue {
+    /// Convert a toml table to [Self], applying options that depend on key paths.
+    pub fn from_table(table: &toml::Table, options: &MacroOptions) -> Result<Self, syn::Error> {
+        Self::from_toml_table(table, &mut Vec::new(), options)
+    }
+
+    /// Constants that check tables mapped to struct literals without declared fields.
+    ///
+    /// Their fields are only known to the compiler, so they are checked when the constants are.
+    pub fn field_checks(
+        table: &toml::Table,
+        options: &MacroOptions,
+        runtime_path: &syn::Path,
+    ) -> pm2::TokenStream {
+        let mut checks = Vec::new();
+        Self::table_field_checks(table, &mut Vec::new(), options, runtime_path, &mut checks);
+
+        checks.into_iter().collect()
+    }
+
+    fn table_field_checks(
+        table: &toml::Table,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+        runtime_path: &syn::Path,
+        checks: &mut Vec<pm2::TokenStream>,
+    ) {
+        for (key, value) in table {
+            path.push(key.to_string());
+            Self::value_field_checks(value, path, options, runtime_path, checks);
+            path.pop();
+        }
+    }
+
+    fn value_field_checks(
+        value: &toml::Value,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+        runtime_path: &syn::Path,
+        checks: &mut Vec<pm2::TokenStream>,
+    ) {
+        let mapped = options.mapped_table(path);
+
+        match value {
+            toml::Value::Table(table) => {
+                if let Some(mapped) = mapped {
+                    checks.extend(mapped.target.field_check(table.keys(), path, runtime_path));
+                }
+                Self::table_field_checks(table, path, options, runtime_path, checks);
+            }
+            // arrays of tables are checked as a whole, like in from_toml_value
+            toml::Value::Array(values) => match mapped {
+                Some(mapped) => {
+                    let tables = values.iter().filter_map(|v| v.as_table());
+
+                    let mut keys = Vec::new();
+                    for key in tables.clone().flat_map(|t| t.keys()) {
+                        if !keys.contains(&key) {
+                            keys.push(key);
+                        }
+                    }
+                    checks.extend(mapped.target.field_check(keys, path, runtime_path));
+
+                    for table in tables {
+                        Self::table_field_checks(table, path, options, runtime_path, checks);
+                    }
+                }
+                None => {
+                    for value in values {
+                        Self::value_field_checks(value, path, options, runtime_path, checks);
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    fn from_toml_table(
+        table: &toml::Table,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<Self, syn::Error> {
+        table
+            .iter()
+            .map(|(key, value)| {
+                path.push(key.to_string());
+                let converted = Self::from_toml_value(value, path, options);
+                path.pop();
+
+                converted.map(|c| (key.to_string(), c))
+            })
+            .collect::<Result<IndexMap<_, _>, _>>()
+            .map(TomlValue::Table)
+    }
+
+    fn from_toml_value(
+        value: &toml::Value,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<Self, syn::Error> {
+        match value {
+            toml::Value::Array(values) => {
+                if let Some(flags) = options.flags(path) {
+                    return Self::from_flags(values, flags, path);
+                }
+
+                let tagged = options.tagged_array(path);
+                let mapped = options.mapped_table(path);
+
+                // keys missing from some tables are filled in, so all tables are checked at once
+                if let Some(mapped) = mapped {
+                    let mut keys = Vec::new();
+                    for value in values {
+                        let table = value.as_table().ok_or_else(|| {
+                            syn::Error::new(
+                                mapped.path.span(),
+                                format!(
+                                    "elements of {} must be tables to be mapped to {}",
+                                    path.join("."),
+                                    mapped.target
+                                ),
+                            )
+                        })?;
+
+                        for key in table.keys() {
+                            if !keys.contains(&key) {
+                                keys.push(key);
+                            }
+                        }
+                    }
+
+                    Self::check_mapped_keys(mapped, keys, path)?;
+                }
+
+                let elements = values
+                    .iter()
+                    .map(|v| match (tagged, mapped, v) {
+                        (Some(t), _, _) => Self::from_tagged_element(v, t, path, options),
+                        (None, Some(m), toml::Value::Table(table)) => {
+                            Self::from_mapped_table(table, m, path, options)
+                        }
+                        _ => Self::from_toml_value(v, path, options),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // fixed arrays are resolved to fixed-size arrays or tuples when normalized
+                let array = match options.is_fixed_array(path) {
+                    true => TomlValue::Tuple(elements),
+                    false => TomlValue::Array(elements),
+                };
+
+                match options.indexed_array(path) {
+                    Some(indexed) => {
+                        Self::check_index_keys(values, indexed, path)?;
+                        Ok(TomlValue::Indexed {
+                            key: indexed.key.clone(),
+                            array: Box::new(array),
+                        })
+                    }
+                    None => Ok(array),
+                }
+            }
+            toml::Value::Table(table) => match options.value_type(path) {
+                Some(value_type) => Err(syn::Error::new(
+                    value_type.path.span(),
+                    format!(
+                        "{} is a table and cannot be generated as {}",
+                        path.join("."),
+                        value_type.ty
+                    ),
+                )),
+                None => match options.mapped_table(path) {
+                    Some(mapped) => {
+                        Self::check_mapped_keys(mapped, table.keys(), path)?;
+                        Self::from_mapped_table(table, mapped, path, options)
+                    }
+                    None => Self::from_toml_table(table, path, options),
+                },
+            },
+            other => match (options.value_type(path), options.enum_values(path)) {
+                // values are checked here, where the key path is known
+                (Some(value_type), _) => value_type
+                    .ty
+                    .check(other)
+                    .map(|_| TomlValue::Typed(value_type.ty))
+                    .map_err(|msg| {
+                        syn::Error::new(
+                            value_type.path.span(),
+                            format!("{}: {}", path.join("."), msg),
+                        )
+                    }),
+                (None, Some(enum_values)) => Self::from_enum_value(other, enum_values, path),
+                (None, None) => Ok(other.clone().into()),
+            },
+        }
+    }
+
+    /// Convert a string to a [TomlValue::Enum] with its allowed values.
+    ///
+    /// Inferred enums only contain the value itself, and are merged when normalized.
+    fn from_enum_value(
+        value: &toml::Value,
+        enum_values: &ValueSet,
+        path: &[String],
+    ) -> Result<Self, syn::Error> {
+        let error = |msg: String| {
+            syn::Error::new(
+                enum_values.path.span(),
+                format!("{}: {}", path.join("."), msg),
+            )
+        };
+
+        let value = value
+            .as_str()
+            .ok_or_else(|| error(format!("expected a string, found {}", value.type_str())))?;
+
+        match &enum_values.values {
+            Some(values) if values.iter().any(|v| v == value) => {
+                Ok(TomlValue::Enum(values.clone()))
+            }
+            Some(values) => Err(error(format!(
+                "\"{}\" is not one of {}",
+                value,
+                values
+                    .iter()
+                    .map(|v| format!("\"{}\"", v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+            None => Ok(TomlValue::Enum(vec![value.to_string()])),
+        }
+    }
+
+    /// Convert an array of strings to [TomlValue::Flags] with all flags.
+    ///
+    /// Inferred flags only contain the values in the array, and are merged when normalized.
+    fn from_flags(
+        values: &[toml::Value],
+        flags: &ValueSet,
+        path: &[String],
+    ) -> Result<Self, syn::Error> {
+        let error = |msg: String| {
+            syn::Error::new(flags.path.span(), format!("{}: {}", path.join("."), msg))
+        };
+
+        let mut all_flags = flags.values.clone().unwrap_or_default();
+        for value in values {
+            let flag = value.as_str().ok_or_else(|| {
+                error(format!(
+                    "expected a string flag, found {}",
+                    value.type_str()
+                ))
+            })?;
+
+            match (&flags.values, all_flags.iter().any(|f| f == flag)) {
+                (_, true) => (),
+                (None, false) => all_flags.push(flag.to_string()),
+                (Some(_), false) => {
+                    return Err(error(format!(
+                        "\"{}\" is not one of {}",
+                        flag,
+                        all_flags
+                            .iter()
+                            .map(|f| format!("\"{}\"", f))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )))
+                }
+            }
+        }
+
+        match all_flags.len() > MAX_FLAGS {
+            true => Err(error(format!(
+                "flags cannot have more than {} values",
+                MAX_FLAGS
+            ))),
+            false => Ok(TomlValue::Flags(all_flags)),
+        }
+    }
+
+    /// Convert a table to a [TomlValue::Mapped]. Keys are checked separately.
+    fn from_mapped_table(
+        table: &toml::Table,
+        mapped: &MappedTable,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<Self, syn::Error> {
+        let table = Self::from_toml_table(table, path, options)?;
+
+        // user-defined types cannot hold an index
+        if let TomlValue::Table(tab) = &table {
+            if let Some(key) = tab
+                .iter()
+                .find_map(|(k, v)| matches!(v, TomlValue::Indexed { .. }).then(|| k))
+            {
+                return Err(syn::Error::new(
+                    mapped.path.span(),
+                    format!(
+                        "{}.{} cannot be indexed, as {} is mapped to {}",
+                        path.join("."),
+                        key,
+                        path.join("."),
+                        mapped.target
+                    ),
+                ));
+            }
+        }
+
+        Ok(TomlValue::Mapped {
+            target: mapped.target.clone(),
+            table: Box::new(table),
+        })
+    }
+
+    /// Check that every table in an indexed array has a unique string value for the key.
+    fn check_index_keys(
+        values: &[toml::Value],
+        indexed: &IndexedArray,
+        path: &[String],
+    ) -> Result<(), syn::Error> {
+        let error = |msg: String| syn::Error::new(indexed.path.span(), msg);
+
+        let mut seen = Vec::new();
+        for value in values {
+            let table = value.as_table().ok_or_else(|| {
+                error(format!(
+                    "elements of {} must be tables to be indexed by \"{}\"",
+                    path.join("."),
+                    indexed.key
+                ))
+            })?;
+
+            let key = match table.get(&indexed.key) {
+                Some(toml::Value::String(key)) => key,
+                Some(other) => {
+                    return Err(error(format!(
+                        "index key \"{}\" in {} must be a string, found {}",
+                        indexed.key,
+                        path.join("."),
+                        other.type_str()
+                    )))
+                }
+                None => {
+                    return Err(error(format!(
+                        "a table in {} is missing the index key \"{}\"",
+                        path.join("."),
+                        indexed.key
+                    )))
+                }
+            };
+
+            if seen.contains(&key) {
+                return Err(error(format!(
+                    "{} contains \"{}\" = \"{}\" more than once",
+                    path.join("."),
+                    indexed.key,
+                    key
+                )));
+            }
+            seen.push(key);
+        }
+
+        Ok(())
+    }
+
+    fn check_mapped_keys<'a>(
+        mapped: &MappedTable,
+        keys: impl IntoIterator<Item = &'a String>,
+        path: &[String],
+    ) -> Result<(), syn::Error> {
+        mapped.target.check_keys(keys).map_err(|msg| {
+            syn::Error::new(mapped.path.span(), format!("{}: {}", path.join("."), msg))
+        })
+    }
+
+    /// Convert a table in a tagged array to a [TomlValue::Tagged] with a single variant.
+    fn from_tagged_element(
+        value: &toml::Value,
+        tagged: &TaggedArray,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<Self, syn::Error> {
+        let error = |msg: String| syn::Error::new(tagged.path.span(), msg);
+
+        let table = value.as_table().ok_or_else(|| {
+            error(format!(
+                "elements of {} must be tables to be tagged by \"{}\"",
+                path.join("."),
+                tagged.tag
+            ))
+        })?;
+
+        let variant = match table.get(&tagged.tag) {
+            Some(toml::Value::String(variant)) => variant,
+            Some(_) => {
+                return Err(error(format!(
+                    "tag \"{}\" in {} must be a string",
+                    tagged.tag,
+                    path.join(".")
+                )))
+            }
+            None => {
+                return Err(error(format!(
+                    "a table in {} is missing the tag \"{}\"",
+                    path.join("."),
+                    tagged.tag
+                )))
+            }
+        };
+
+        let mut fields = table.clone();
+        fields.remove(&tagged.tag);
+
+        let mut variants = IndexMap::new();
+        variants.insert(
+            variant.to_string(),
+            Self::from_toml_table(&fields, path, options)?,
+        );
+
+        Ok(TomlValue::Tagged {
+            tag: tagged.tag.clone(),
+            variants,
+        })
+    }
+
     /// This method assumes that [TomlValue::normalize] is already called.
     ///
     /// This will recursively visit and normalize all items in a [toml::Value].
@@ -175,6 +858,17 @@ impl TomlValue {
             | (TomlValue::Float, toml::Value::Float(_))
             | (TomlValue::Boolean, toml::Value::Boolean(_)) => (),
 
+            // checked when converted
+            (TomlValue::Typed(_), _)
+            | (TomlValue::Enum(_), toml::Value::String(_))
+            | (TomlValue::Flags(_), toml::Value::Array(_)) => (),
+
+            // integers promoted to floats
+            (TomlValue::Float, toml_value @ toml::Value::Integer(_)) => {
+                let int = toml_value.as_integer().expect("already matched");
+                *toml_value = toml::Value::Float(int as f64);
+            }
+
             (
                 TomlValue::Datetime {
                     date: tv_date,
@@ -202,6 +896,18 @@ impl TomlValue {
                     }
                 }
             }
+            (TomlValue::FixedArray { element, .. }, toml::Value::Array(values)) => {
+                if let Some(toml_value) = element {
+                    for val in values {
+                        toml_value.normalize_toml(val);
+                    }
+                }
+            }
+            (TomlValue::Tuple(toml_values), toml::Value::Array(values)) => {
+                for (toml_value, val) in toml_values.iter().zip(values) {
+                    toml_value.normalize_toml(val);
+                }
+            }
             (TomlValue::Table(hash_map), toml::Value::Table(map)) => {
                 for (key, value) in hash_map {
                     match (map.get_mut(key), value) {
@@ -209,7 +915,7 @@ impl TomlValue {
                             value.normalize_toml(toml_value);
                         }
                         // for missing keys that point to arrays, we initialize them as empty arrays
-                        (None, TomlValue::Array(_)) => {
+                        (None, TomlValue::Array(_) | TomlValue::Indexed { .. }) => {
                             map.insert(key.to_owned(), toml::Value::Array(vec![]));
                         }
                         (None, _) => {
@@ -218,12 +924,7 @@ impl TomlValue {
                     }
                 }
             }
-            (
-                TomlValue::TableMap {
-                    keys, value_type, ..
-                },
-                toml::Value::Table(map),
-            ) => {
+            (TomlValue::TableMap { keys, value_type }, toml::Value::Table(map)) => {
                 for key in keys {
                     match (map.get_mut(key), value_type.as_ref()) {
                         (Some(toml_value), _) => {
@@ -238,6 +939,22 @@ impl TomlValue {
                     }
                 }
             }
+            (TomlValue::Tagged { tag, variants }, toml_value @ toml::Value::Table(_)) => {
+                let variant = toml_value
+                    .get(tag)
+                    .and_then(|t| t.as_str())
+                    .and_then(|t| variants.get(t))
+                    .expect("tag must select a variant");
+                variant.normalize_toml(toml_value);
+            }
+            (TomlValue::Mapped { table, .. }, toml_value) => table.normalize_toml(toml_value),
+            (TomlValue::Indexed { array, .. }, toml_value) => array.normalize_toml(toml_value),
+            (TomlValue::Union(_), toml_value) => {
+                let member = self
+                    .union_member(toml_value)
+                    .expect("value must be a member of the union");
+                member.normalize_toml(toml_value);
+            }
             _ => unimplemented!("normalizing different types cannot be done"),
         }
     }
@@ -245,14 +962,14 @@ impl TomlValue {
     /// Derive a normalized version of [Self].
     ///
     /// At this point, the schema of [Self] will be superset of the original.
-    pub fn normalize(self) -> Result<Self, NormalizationError> {
+    pub fn normalize(self, options: &MacroOptions) -> Result<Self, NormalizationError> {
         match self {
             TomlValue::Array(toml_values) => match toml_values.first() {
                 Some(first) => {
-                    let first_val = first.clone();
+                    let first_val = first.clone().normalize(options)?;
                     let normalized = toml_values.into_iter().try_fold(first_val, |acc, item| {
-                        let inter = item.normalize()?;
-                        acc.union(&inter)
+                        let inter = item.normalize(options)?;
+                        acc.union(&inter, options)
                     })?;
 
                     Ok(TomlValue::Array(vec![normalized]))
@@ -260,11 +977,39 @@ impl TomlValue {
                 None => Ok(TomlValue::Array(vec![])),
             },
 
+            // arrays with elements of the same type are fixed-size arrays, otherwise tuples
+            TomlValue::Tuple(elements) => {
+                let normalized = elements
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, e)| {
+                        e.normalize(options)
+                            .map_err(|err| err.propagate(&i.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut items = normalized.iter();
+                let element = match items.next() {
+                    Some(first) => items
+                        .try_fold(first.clone(), |acc, item| acc.union(item, options))
+                        .map(Some),
+                    None => Ok(None),
+                };
+
+                match element {
+                    Ok(element) => Ok(TomlValue::FixedArray {
+                        len: normalized.len(),
+                        element: element.map(Box::new),
+                    }),
+                    Err(_) => Ok(TomlValue::Tuple(normalized)),
+                }
+            }
+
             TomlValue::Table(toml_table) => {
                 let norm_table = toml_table
                     .into_iter()
                     .map(|(k, v)| {
-                        let normalized_value = v.normalize();
+                        let normalized_value = v.normalize(options);
                         match normalized_value {
                             Ok(nv) => Ok((k.clone(), nv)),
                             Err(e) => Err(e.propagate(&k)),
@@ -279,69 +1024,202 @@ impl TomlValue {
                 Ok(Self::resolve_date_time_offset(date, time, offset))
             }
 
+            TomlValue::Tagged { tag, variants } => {
+                let variants = variants
+                    .into_iter()
+                    .map(|(k, v)| match v.normalize(options) {
+                        Ok(nv) => Ok((k, nv)),
+                        Err(e) => Err(e.propagate(&k)),
+                    })
+                    .collect::<Result<IndexMap<_, _>, _>>()?;
+
+                Ok(TomlValue::Tagged { tag, variants })
+            }
+
+            TomlValue::Mapped { target, table } => Ok(TomlValue::Mapped {
+                target,
+                table: Box::new(table.normalize(options)?),
+            }),
+
+            TomlValue::Indexed { key, array } => Ok(TomlValue::Indexed {
+                key,
+                array: Box::new(array.normalize(options)?),
+            }),
+
             // everything else is already normalized
             other => Ok(other),
         }
     }
 
-    /// Transform tables with identical values to table maps
-    #[cfg(feature = "phf")]
-    pub fn reduce(self) -> Self {
+    /// Transform tables with identical values to table maps.
+    ///
+    /// Tables at paths of [MacroOptions::table_maps] are always table maps, with the union of their values as the value type.
+    /// Tables at paths of [MacroOptions::no_table_maps] never are.
+    pub fn reduce(self, options: &MacroOptions) -> Result<Self, NormalizationError> {
+        self.reduce_at(&mut Vec::new(), options)
+    }
+
+    fn reduce_at(
+        self,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<Self, NormalizationError> {
         match self {
+            TomlValue::Table(tab) if tab.is_empty() => Ok(TomlValue::Table(tab)),
+
+            // values are merged before they are reduced, so that their inner tables are merged too
+            TomlValue::Table(tab) if options.table_map(path) == Some(true) => {
+                let keys = tab.keys().cloned().collect::<Vec<_>>();
+                let mut values = tab.into_iter();
+                let (first_key, first_val) =
+                    values.next().expect("already checked for empty table");
+                let merged = values.try_fold(first_val, |acc, (k, v)| {
+                    acc.union(&v, options).map_err(|e| e.propagate(&k))
+                })?;
+
+                // the value type is reduced at the path of the first key
+                path.push(first_key.clone());
+                let value_type = merged.reduce_at(path, options);
+                path.pop();
+
+                Ok(TomlValue::TableMap {
+                    keys,
+                    value_type: Box::new(value_type.map_err(|e| e.propagate(&first_key))?),
+                })
+            }
+
             TomlValue::Table(tab) => {
-                match tab.len() {
-                    0 => TomlValue::Table(tab),
-                    _ => {
-                        // reduce inner first
-                        let reduced_inner = tab
-                            .into_iter()
-                            .map(|(k, v)| (k, v.reduce()))
-                            .collect::<IndexMap<_, _>>();
-
-                        // let mut key_values = reduced_inner.iter();
-                        // let (first_key, first_value) =
-                        //     key_values.next().expect("already checked for empty table");
-
-                        let (keys, values): (Vec<_>, Vec<_>) = reduced_inner.into_iter().unzip();
-                        let first_val = &values[0];
-                        let first_key = keys[0].to_string();
-
-                        if values.iter().all(|v| first_val == v) {
-                            TomlValue::TableMap {
-                                keys,
-                                first: first_key,
-                                value_type: Box::new(first_val.clone()),
-                            }
-                        } else {
-                            TomlValue::Table((keys.into_iter()).zip(values).collect())
-                        }
-                    }
+                // reduce inner first
+                let reduced_inner = Self::reduce_table(tab, path, options)?;
+
+                let (keys, values): (Vec<_>, Vec<_>) = reduced_inner.into_iter().unzip();
+                let first_val = &values[0];
+
+                let indexed = values
+                    .iter()
+                    .any(|v| matches!(v, TomlValue::Indexed { .. }));
+                let suppressed = options.table_map(path) == Some(false);
+
+                if !indexed && !suppressed && values.iter().all(|v| first_val == v) {
+                    Ok(TomlValue::TableMap {
+                        keys,
+                        value_type: Box::new(first_val.clone()),
+                    })
+                } else {
+                    Ok(TomlValue::Table((keys.into_iter()).zip(values).collect()))
                 }
             }
 
-            TomlValue::Array(arr) => {
-                TomlValue::Array(arr.into_iter().map(|a| a.reduce()).collect())
-            }
+            // array elements share the key path of their array
+            TomlValue::Array(arr) => arr
+                .into_iter()
+                .map(|a| a.reduce_at(path, options))
+                .collect::<Result<_, _>>()
+                .map(TomlValue::Array),
+            TomlValue::Union(members) => members
+                .into_iter()
+                .map(|m| m.reduce_at(path, options))
+                .collect::<Result<_, _>>()
+                .map(TomlValue::Union),
+            TomlValue::FixedArray { len, element } => Ok(TomlValue::FixedArray {
+                len,
+                element: match element {
+                    Some(e) => Some(Box::new(e.reduce_at(path, options)?)),
+                    None => None,
+                },
+            }),
+            TomlValue::Tuple(elements) => elements
+                .into_iter()
+                .map(|e| e.reduce_at(path, options))
+                .collect::<Result<_, _>>()
+                .map(TomlValue::Tuple),
+            // variants are always generated as structs, only their values are reduced
+            TomlValue::Tagged { tag, variants } => Ok(TomlValue::Tagged {
+                tag,
+                variants: variants
+                    .into_iter()
+                    .map(|(k, v)| match v {
+                        TomlValue::Table(tab) => Self::reduce_table(tab, path, options)
+                            .map(|tab| (k, TomlValue::Table(tab))),
+                        other => other.reduce_at(path, options).map(|v| (k, v)),
+                    })
+                    .collect::<Result<_, _>>()?,
+            }),
+            // mapped tables are never reduced to maps, only their values are
+            TomlValue::Mapped { target, table } => Ok(TomlValue::Mapped {
+                target,
+                table: Box::new(match *table {
+                    TomlValue::Table(tab) => {
+                        TomlValue::Table(Self::reduce_table(tab, path, options)?)
+                    }
+                    other => other.reduce_at(path, options)?,
+                }),
+            }),
+            TomlValue::Indexed { key, array } => Ok(TomlValue::Indexed {
+                key,
+                array: Box::new(array.reduce_at(path, options)?),
+            }),
             // no need to reduce primitive types
-            other => other,
+            other => Ok(other),
         }
     }
 
-    #[cfg(not(feature = "phf"))]
-    pub fn reduce(self) -> Self {
-        self
+    /// Reduce the values of a table at their key paths.
+    fn reduce_table(
+        tab: IndexMap<String, TomlValue>,
+        path: &mut Vec<String>,
+        options: &MacroOptions,
+    ) -> Result<IndexMap<String, TomlValue>, NormalizationError> {
+        tab.into_iter()
+            .map(|(k, v)| {
+                path.push(k.clone());
+                let reduced = v.reduce_at(path, options);
+                path.pop();
+
+                match reduced {
+                    Ok(r) => Ok((k, r)),
+                    Err(e) => Err(e.propagate(&k)),
+                }
+            })
+            .collect()
     }
 
     /// Calculate the union of two [TomlValue] types.
     ///
     /// This will first check if both types are the same, and then merge table and array types.
     /// Arrays will be reduced to lengths 1 or 0.
-    fn union(&self, other: &Self) -> Result<Self, NormalizationError> {
+    ///
+    /// If [MacroOptions::promote_numbers] is set, integers and floats are unified to floats.
+    /// If [MacroOptions::mixed_arrays] is set, distinct types are unified to a [TomlValue::Union].
+    fn union(&self, other: &Self, options: &MacroOptions) -> Result<Self, NormalizationError> {
         match (self, other) {
             (TomlValue::String, TomlValue::String) => Ok(TomlValue::String),
             (TomlValue::Integer, TomlValue::Integer) => Ok(TomlValue::Integer),
             (TomlValue::Float, TomlValue::Float) => Ok(TomlValue::Float),
+            (TomlValue::Integer, TomlValue::Float) | (TomlValue::Float, TomlValue::Integer)
+                if options.promote_numbers =>
+            {
+                Ok(TomlValue::Float)
+            }
             (TomlValue::Boolean, TomlValue::Boolean) => Ok(TomlValue::Boolean),
+            (TomlValue::Typed(ty_self), TomlValue::Typed(ty_other)) if ty_self == ty_other => {
+                Ok(TomlValue::Typed(*ty_self))
+            }
+            // inferred values are merged in the order they are found
+            (TomlValue::Enum(values_self), TomlValue::Enum(values_other)) => {
+                Ok(TomlValue::Enum(merge_values(values_self, values_other)))
+            }
+            (TomlValue::Flags(flags_self), TomlValue::Flags(flags_other)) => {
+                let merged = merge_values(flags_self, flags_other);
+
+                match merged.len() > MAX_FLAGS {
+                    true => Err(NormalizationError::ValueMismatch {
+                        path: vec![],
+                        value_types: Box::new((self.clone(), other.clone())),
+                    }),
+                    false => Ok(TomlValue::Flags(merged)),
+                }
+            }
             (
                 TomlValue::Datetime {
                     date: ld,
@@ -367,7 +1245,7 @@ impl TomlValue {
                         let merged = arr_self
                             .iter()
                             .chain(arr_other.iter())
-                            .try_fold(first.to_owned(), |acc, item| acc.union(item))?;
+                            .try_fold(first.to_owned(), |acc, item| acc.union(item, options))?;
 
                         Ok(TomlValue::Array(vec![merged]))
                     }
@@ -375,15 +1253,71 @@ impl TomlValue {
                 }
             }
 
-            (TomlValue::Table(tab_self), TomlValue::Table(tab_other)) => {
-                let mut merged = tab_self.clone();
+            // fixed arrays with different lengths are demoted to arrays
+            (
+                TomlValue::FixedArray {
+                    len: len_self,
+                    element: element_self,
+                },
+                TomlValue::FixedArray {
+                    len: len_other,
+                    element: element_other,
+                },
+            ) => {
+                let element =
+                    union_elements(element_self.as_deref(), element_other.as_deref(), options)?;
+
+                match len_self == len_other {
+                    true => Ok(TomlValue::FixedArray {
+                        len: *len_self,
+                        element: element.map(Box::new),
+                    }),
+                    false => Ok(TomlValue::Array(element.into_iter().collect())),
+                }
+            }
+            (TomlValue::Array(arr), TomlValue::FixedArray { element, .. })
+            | (TomlValue::FixedArray { element, .. }, TomlValue::Array(arr)) => {
+                let element = union_elements(arr.first(), element.as_deref(), options)?;
 
-                for (key, value) in tab_other {
-                    match merged.get_mut(key) {
-                        Some(existing_val) => {
-                            match existing_val.union(value) {
-                                Ok(u) => *existing_val = u,
-                                Err(e) => Err(e.propagate(key))?,
+                Ok(TomlValue::Array(element.into_iter().collect()))
+            }
+
+            (TomlValue::Tuple(tup_self), TomlValue::Tuple(tup_other))
+                if tup_self.len() == tup_other.len() =>
+            {
+                tup_self
+                    .iter()
+                    .zip(tup_other)
+                    .enumerate()
+                    .map(|(i, (a, b))| a.union(b, options).map_err(|e| e.propagate(&i.to_string())))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(TomlValue::Tuple)
+            }
+            (TomlValue::Tuple(tup), TomlValue::FixedArray { len, element })
+            | (TomlValue::FixedArray { len, element }, TomlValue::Tuple(tup))
+                if tup.len() == *len =>
+            {
+                let element = element.as_deref().expect("non-empty fixed array");
+
+                tup.iter()
+                    .enumerate()
+                    .map(|(i, a)| {
+                        a.union(element, options)
+                            .map_err(|e| e.propagate(&i.to_string()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(TomlValue::Tuple)
+            }
+
+            (TomlValue::Table(tab_self), TomlValue::Table(tab_other)) => {
+                let mut merged = tab_self.clone();
+
+                for (key, value) in tab_other {
+                    match merged.get_mut(key) {
+                        Some(existing_val) => {
+                            match existing_val.union(value, options) {
+                                Ok(u) => *existing_val = u,
+                                Err(e) => Err(e.propagate(key))?,
                             };
                         }
                         None => {
@@ -395,6 +1329,66 @@ impl TomlValue {
                 Ok(TomlValue::Table(merged))
             }
 
+            (
+                TomlValue::Tagged {
+                    tag: tag_self,
+                    variants: variants_self,
+                },
+                TomlValue::Tagged {
+                    tag: tag_other,
+                    variants: variants_other,
+                },
+            ) if tag_self == tag_other => {
+                let mut merged = variants_self.clone();
+
+                for (variant, value) in variants_other {
+                    match merged.get_mut(variant) {
+                        Some(existing_val) => match existing_val.union(value, options) {
+                            Ok(u) => *existing_val = u,
+                            Err(e) => Err(e.propagate(variant))?,
+                        },
+                        None => {
+                            merged.insert(variant.to_string(), value.clone());
+                        }
+                    }
+                }
+
+                Ok(TomlValue::Tagged {
+                    tag: tag_self.clone(),
+                    variants: merged,
+                })
+            }
+
+            (
+                TomlValue::Mapped {
+                    target: target_self,
+                    table: table_self,
+                },
+                TomlValue::Mapped {
+                    target: target_other,
+                    table: table_other,
+                },
+            ) if target_self == target_other => Ok(TomlValue::Mapped {
+                target: target_self.clone(),
+                table: Box::new(table_self.union(table_other, options)?),
+            }),
+
+            (
+                TomlValue::Indexed {
+                    key: key_self,
+                    array: array_self,
+                },
+                TomlValue::Indexed {
+                    key: key_other,
+                    array: array_other,
+                },
+            ) if key_self == key_other => Ok(TomlValue::Indexed {
+                key: key_self.clone(),
+                array: Box::new(array_self.union(array_other, options)?),
+            }),
+
+            (_, _) if options.mixed_arrays => self.union_members(other, options),
+
             err_other => Err(NormalizationError::ValueMismatch {
                 path: vec![],
                 value_types: Box::new((err_other.0.clone(), err_other.1.clone())),
@@ -402,6 +1396,92 @@ impl TomlValue {
         }
     }
 
+    /// Merge two values of different types into a [TomlValue::Union].
+    ///
+    /// Members of the same kind are merged with [TomlValue::union].
+    fn union_members(
+        &self,
+        other: &Self,
+        options: &MacroOptions,
+    ) -> Result<Self, NormalizationError> {
+        let mut members: Vec<TomlValue> = Vec::new();
+
+        for item in self.members().iter().chain(other.members()) {
+            let item_kind = item.member_kind(options);
+
+            match members
+                .iter_mut()
+                .find(|m| m.member_kind(options) == item_kind)
+            {
+                Some(existing) => *existing = existing.union(item, options)?,
+                None => members.push(item.clone()),
+            }
+        }
+
+        members.sort_by_key(|m| m.member_kind(options));
+
+        match members.len() {
+            1 => Ok(members.remove(0)),
+            _ => Ok(TomlValue::Union(members)),
+        }
+    }
+
+    /// Members of a [TomlValue::Union], or a slice containing only `self`.
+    fn members(&self) -> &[TomlValue] {
+        match self {
+            TomlValue::Union(members) => members,
+            other => std::slice::from_ref(other),
+        }
+    }
+
+    /// Kind of a value when it is a member of a [TomlValue::Union].
+    ///
+    /// Integers are of the same kind as floats if they can be promoted.
+    fn member_kind(&self, options: &MacroOptions) -> MemberKind {
+        match self {
+            TomlValue::String | TomlValue::Enum(_) => MemberKind::String,
+            TomlValue::Integer if options.promote_numbers => MemberKind::Float,
+            TomlValue::Integer => MemberKind::Integer,
+            TomlValue::Float => MemberKind::Float,
+            TomlValue::Boolean => MemberKind::Boolean,
+            TomlValue::Typed(ValueType::Int(_) | ValueType::NonZero(_) | ValueType::ByteSize) => {
+                MemberKind::Integer
+            }
+            TomlValue::Typed(value_type) if value_type.is_float() => MemberKind::Float,
+            // other types are parsed from strings
+            TomlValue::Typed(_) => MemberKind::String,
+            TomlValue::Datetime { .. } => MemberKind::Datetime,
+            TomlValue::Array(_)
+            | TomlValue::FixedArray { .. }
+            | TomlValue::Tuple(_)
+            | TomlValue::Flags(_)
+            | TomlValue::Indexed { .. } => MemberKind::Array,
+            TomlValue::Table(_)
+            | TomlValue::TableMap { .. }
+            | TomlValue::Tagged { .. }
+            | TomlValue::Mapped { .. } => MemberKind::Table,
+            TomlValue::Union(_) => unimplemented!("unions cannot be nested"),
+        }
+    }
+
+    /// Find the member of a [TomlValue::Union] that a value is instantiated as.
+    ///
+    /// Integers resolve to a float member if there is no integer member.
+    pub fn union_member(&self, value: &toml::Value) -> Option<&TomlValue> {
+        let value_kind = MemberKind::of_value(value);
+        let members = self.members();
+
+        members
+            .iter()
+            .find(|m| m.member_kind(&MacroOptions::default()) == value_kind)
+            .or_else(|| match value_kind {
+                MemberKind::Integer => members
+                    .iter()
+                    .find(|m| m.member_kind(&MacroOptions::default()) == MemberKind::Float),
+                _ => None,
+            })
+    }
+
     /// Some date-time combinations are not valid
     fn resolve_date_time_offset(date: bool, time: bool, offset: bool) -> TomlValue {
         match (date, time, offset) {
@@ -435,6 +1515,74 @@ impl TomlValue {
         }
     }
 
+    /// Types that the definition of a value declares in its parent module.
+    ///
+    /// Besides the type of the value, these are the key enums of table maps, e.g. `ServersKey`,
+    /// and the types of tuple elements and union members, which are defined next to it.
+    fn defined_types(&self, key: &str, naming: &Naming) -> Vec<Ident> {
+        match self {
+            TomlValue::Table(_)
+            | TomlValue::Tagged { .. }
+            | TomlValue::Enum(_)
+            | TomlValue::Flags(_) => vec![key.to_type_ident(naming)],
+            TomlValue::TableMap { .. } => vec![
+                key.to_type_ident(naming),
+                format!("{}_key", key).to_type_ident(naming),
+            ],
+            TomlValue::Union(members) => std::iter::once(key.to_item_type_ident(naming))
+                .chain(
+                    members
+                        .iter()
+                        .flat_map(|m| m.defined_types(&union_member_key(key, m), naming)),
+                )
+                .collect(),
+            TomlValue::Tuple(elements) => elements
+                .iter()
+                .enumerate()
+                .flat_map(|(i, e)| e.defined_types(&tuple_element_key(key, i), naming))
+                .collect(),
+            TomlValue::Array(arr) => arr
+                .first()
+                .map(|e| e.defined_types(key, naming))
+                .unwrap_or_default(),
+            TomlValue::FixedArray { element, .. } => element
+                .as_ref()
+                .map(|e| e.defined_types(key, naming))
+                .unwrap_or_default(),
+            TomlValue::Indexed { array, .. } => array.defined_types(key, naming),
+            _ => vec![],
+        }
+    }
+
+    /// Modules that the definition of a value declares in its parent module.
+    fn defined_modules(&self, key: &str) -> Vec<Ident> {
+        match self {
+            TomlValue::Table(_)
+            | TomlValue::TableMap { .. }
+            | TomlValue::Tagged { .. }
+            | TomlValue::Mapped { .. } => vec![key.to_type_module_ident()],
+            TomlValue::Union(members) => members
+                .iter()
+                .flat_map(|m| m.defined_modules(&union_member_key(key, m)))
+                .collect(),
+            TomlValue::Tuple(elements) => elements
+                .iter()
+                .enumerate()
+                .flat_map(|(i, e)| e.defined_modules(&tuple_element_key(key, i)))
+                .collect(),
+            TomlValue::Array(arr) => arr
+                .first()
+                .map(|e| e.defined_modules(key))
+                .unwrap_or_default(),
+            TomlValue::FixedArray { element, .. } => element
+                .as_ref()
+                .map(|e| e.defined_modules(key))
+                .unwrap_or_default(),
+            TomlValue::Indexed { array, .. } => array.defined_modules(key),
+            _ => vec![],
+        }
+    }
+
     /// Return the type of a value.
     /// Arrays will descend and return their inner type.
     fn ty(
@@ -442,12 +1590,16 @@ impl TomlValue {
         key: &str,
         parent_mod: Option<&Ident>,
         runtime_path: &syn::Path,
+        naming: &Naming,
     ) -> pm2::TokenStream {
         match self {
             TomlValue::String => quote! {&'static str},
             TomlValue::Integer => quote! {i64},
             TomlValue::Float => quote! {f64},
             TomlValue::Boolean => quote! {bool},
+            TomlValue::Typed(value_type) => value_type.ty(runtime_path),
+            TomlValue::Mapped { target, .. } => target.ty(),
+            TomlValue::Indexed { array, .. } => array.ty(key, parent_mod, runtime_path, naming),
             TomlValue::Datetime { date, time, offset } => {
                 let dt_ident = date_time_struct_ident(*date, *time, *offset);
                 quote! { #runtime_path :: #dt_ident }
@@ -455,7 +1607,7 @@ impl TomlValue {
             TomlValue::Array(toml_values) => {
                 match toml_values.first() {
                     Some(inner) => {
-                        let inner_type = inner.ty(key, parent_mod, runtime_path);
+                        let inner_type = inner.ty(key, parent_mod, runtime_path, naming);
 
                         quote! { &'static [#inner_type] }
                     }
@@ -463,8 +1615,35 @@ impl TomlValue {
                     None => quote! { &'static [&'static str] },
                 }
             }
-            TomlValue::Table(_) | TomlValue::TableMap { .. } => {
-                let self_type = key.to_type_ident();
+            TomlValue::FixedArray { len, element } => {
+                let inner_type = match element {
+                    Some(inner) => inner.ty(key, parent_mod, runtime_path, naming),
+                    None => quote! { &'static str },
+                };
+
+                quote! { [#inner_type; #len] }
+            }
+            TomlValue::Tuple(elements) => {
+                let inner_types = elements.iter().enumerate().map(|(i, e)| {
+                    e.ty(&tuple_element_key(key, i), parent_mod, runtime_path, naming)
+                });
+
+                quote! { (#(#inner_types,)*) }
+            }
+            TomlValue::Union(_) => {
+                let self_type = key.to_item_type_ident(naming);
+
+                match parent_mod {
+                    Some(parent) => quote! { #parent :: #self_type },
+                    None => quote! { #self_type },
+                }
+            }
+            TomlValue::Table(_)
+            | TomlValue::TableMap { .. }
+            | TomlValue::Tagged { .. }
+            | TomlValue::Enum(_)
+            | TomlValue::Flags(_) => {
+                let self_type = key.to_type_ident(naming);
 
                 match parent_mod {
                     Some(parent) => quote! { #parent :: #self_type },
@@ -486,13 +1665,55 @@ impl TomlValue {
         key: &str,
         derive_attrs: &[syn::Attribute],
         runtime_path: &syn::Path,
+        naming: &Naming,
+    ) -> pm2::TokenStream {
+        self.define(
+            key,
+            derive_attrs,
+            runtime_path,
+            naming,
+            &mut SharedTypes::default(),
+        )
+    }
+
+    /// Recursively define array and table types, defining one type for each distinct shape.
+    ///
+    /// Later definitions of the same shape are type aliases of the first,
+    /// and their modules re-export the modules of the first.
+    pub fn shared_definition(
+        &self,
+        key: &str,
+        derive_attrs: &[syn::Attribute],
+        runtime_path: &syn::Path,
+        naming: &Naming,
+    ) -> pm2::TokenStream {
+        let mut shared = SharedTypes {
+            enabled: true,
+            ..Default::default()
+        };
+
+        self.define(key, derive_attrs, runtime_path, naming, &mut shared)
+    }
+
+    fn define(
+        &self,
+        key: &str,
+        derive_attrs: &[syn::Attribute],
+        runtime_path: &syn::Path,
+        naming: &Naming,
+        shared: &mut SharedTypes,
     ) -> pm2::TokenStream {
+        if let Some(alias) = shared.alias(self, key, naming) {
+            return alias;
+        }
+
         match self {
             // do not need to define primitive/provided types
             TomlValue::String
             | TomlValue::Integer
             | TomlValue::Float
             | TomlValue::Boolean
+            | TomlValue::Typed(_)
             | TomlValue::Datetime { .. } => quote! {},
 
             TomlValue::Array(arr) => match arr.len() {
@@ -500,13 +1721,39 @@ impl TomlValue {
                 1 => {
                     let inner_value = &arr[0];
 
-                    inner_value.definition(key, derive_attrs, runtime_path)
+                    inner_value.define(key, derive_attrs, runtime_path, naming, shared)
                 }
                 _ => unimplemented!("normalized array should have 0 or 1 elements"),
             },
+            TomlValue::FixedArray { element, .. } => match element {
+                Some(inner_value) => {
+                    inner_value.define(key, derive_attrs, runtime_path, naming, shared)
+                }
+                None => quote! {},
+            },
+            TomlValue::Indexed { array, .. } => {
+                array.define(key, derive_attrs, runtime_path, naming, shared)
+            }
+            TomlValue::Tuple(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    e.define(
+                        &tuple_element_key(key, i),
+                        derive_attrs,
+                        runtime_path,
+                        naming,
+                        shared,
+                    )
+                })
+                .collect(),
             TomlValue::Table(tab) => {
-                let self_ident = key.to_type_ident();
-                let self_mod = key.to_module_ident();
+                let self_ident = key.to_type_ident(naming);
+                let self_mod = key.to_type_module_ident();
+
+                if let Some(error) = key_collision(key, tab, naming) {
+                    return error;
+                }
 
                 // // we make the identifier in all values the same type, if all values in the table are the same.
                 // let same_val_type = match tab.len() {
@@ -530,9 +1777,9 @@ impl TomlValue {
                     .map(|(k, v)| {
                         // x += 1;
                         // let field_ident = k.to_variable_ident();
-                        let field_ident = k.to_module_ident();
+                        let field_ident = k.to_module_ident(naming);
 
-                        let field_type = v.ty(k, Some(&self_mod), runtime_path);
+                        let field_type = v.ty(k, Some(&self_mod), runtime_path, naming);
 
                         quote! {
                             #field_ident: #field_type
@@ -540,27 +1787,93 @@ impl TomlValue {
                     })
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
+                // indexes are private fields after the keys, returned by `<key>_by_<index key>()`
+                let indexes = tab
+                    .iter()
+                    .filter_map(|(k, v)| match v {
+                        TomlValue::Indexed { key: by, array } => Some((k, by, array)),
+                        _ => None,
+                    })
+                    .map(|(k, by, array)| {
+                        let (method, field) = index_idents(k, by, naming);
+                        let element_type = match array.as_ref() {
+                            TomlValue::Array(arr) => arr.first(),
+                            TomlValue::FixedArray { element, .. } => element.as_deref(),
+                            _ => {
+                                let msg =
+                                    format!("{}: indexed arrays must have one element type", k);
+                                return Err(quote! { compile_error!(#msg); });
+                            }
+                        };
+                        let element_type = match element_type {
+                            Some(element) => element.ty(k, Some(&self_mod), runtime_path, naming),
+                            None => quote! { &'static str },
+                        };
+                        let doc = format!("Elements of `{}`, by their `{}`.", k, by);
+
+                        Ok((method, field, element_type, doc))
+                    })
+                    .collect::<Result<Vec<_>, _>>();
+                let indexes = match indexes {
+                    Ok(indexes) => indexes,
+                    Err(error) => return error,
+                };
+                let index_fields = indexes
+                    .iter()
+                    .map(|(_, field, element_type, _)| {
+                        quote! { #field: #runtime_path::ConstMap<#element_type> }
+                    })
+                    .collect::<Vec<_>>();
+                let index_methods = indexes
+                    .iter()
+                    .map(|(method, field, element_type, doc)| {
+                        quote! {
+                            #[doc = #doc]
+                            pub const fn #method(&self) -> #runtime_path::ConstMap<#element_type> {
+                                self.#field
+                            }
+                        }
+                    })
+                    .collect::<pm2::TokenStream>();
+
                 let struct_fields = constructor_fields
                     .iter()
                     .map(|k| {
                         quote! {pub #k}
                     })
+                    .chain(index_fields.clone())
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
+                let constructor_fields = constructor_fields
+                    .into_iter()
+                    .chain(index_fields)
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
-                let inner_definitions = tab
-                    .iter()
-                    .filter(|(_, v)| {
-                        matches!(
-                            v,
-                            TomlValue::Array(_) | TomlValue::Table(_) | TomlValue::TableMap { .. }
-                        )
-                    })
-                    .map(|(k, v)| v.definition(k, derive_attrs, runtime_path))
-                    .collect::<pm2::TokenStream>();
+                let inner_definitions = shared.in_module(&self_mod, |shared| {
+                    tab.iter()
+                        .filter(|(_, v)| {
+                            matches!(
+                                v,
+                                TomlValue::Array(_)
+                                    | TomlValue::FixedArray { .. }
+                                    | TomlValue::Tuple(_)
+                                    | TomlValue::Table(_)
+                                    | TomlValue::TableMap { .. }
+                                    | TomlValue::Union(_)
+                                    | TomlValue::Tagged { .. }
+                                    | TomlValue::Mapped { .. }
+                                    | TomlValue::Indexed { .. }
+                                    | TomlValue::Enum(_)
+                                    | TomlValue::Flags(_)
+                            )
+                        })
+                        .map(|(k, v)| v.define(k, derive_attrs, runtime_path, naming, shared))
+                        .collect::<pm2::TokenStream>()
+                });
 
                 let shorthand_init_fields = tab
                     .iter()
-                    .map(|(k, _)| k.to_module_ident().to_token_stream())
+                    .map(|(k, _)| k.to_module_ident(naming).to_token_stream())
+                    .chain(indexes.iter().map(|(_, field, ..)| field.to_token_stream()))
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
                 let derives = derive_attrs
@@ -571,13 +1884,14 @@ impl TomlValue {
                 quote! {
                     #[derive(Clone, Copy, Debug)]
                     #derives
+                    #[allow(non_snake_case)]
                     pub struct #self_ident {
                         #struct_fields
                     }
 
                     impl #self_ident {
                         #[doc(hidden)]
-                        #[allow(clippy::too_many_arguments)]
+                        #[allow(clippy::too_many_arguments, non_snake_case)]
                         pub const fn new(
                             #constructor_fields
                         ) -> Self {
@@ -585,34 +1899,238 @@ impl TomlValue {
                                 #shorthand_init_fields
                             }
                         }
+
+                        #index_methods
                     }
 
+                    #[allow(non_snake_case)]
                     pub mod #self_mod {
                         #inner_definitions
                     }
                 }
             }
-            TomlValue::TableMap {
-                keys,
-                first,
-                value_type,
-            } => {
-                let self_ident = key.to_type_ident();
-                let self_mod = key.to_module_ident();
-                let all_field_type = value_type.ty(first, Some(&self_mod), runtime_path);
+            TomlValue::Enum(values) => {
+                let self_ident = key.to_type_ident(naming);
+                let variants = values
+                    .iter()
+                    .map(|v| v.to_variant_ident(naming))
+                    .collect::<Vec<_>>();
+
+                if let Some(msg) = collision(key, "variant", values.iter().zip(&variants)) {
+                    return quote! { compile_error!(#msg); };
+                }
+                let derives = derive_attrs
+                    .iter()
+                    .map(|attr| quote! { #attr })
+                    .collect::<pm2::TokenStream>();
+
+                quote! {
+                    #[derive(Clone, Copy, Debug)]
+                    #derives
+                    pub enum #self_ident {
+                        #(#variants,)*
+                    }
+
+                    impl #self_ident {
+                        /// All variants, in the order they are declared.
+                        pub const VARIANTS: &'static [Self] = &[#(Self::#variants,)*];
+
+                        /// The string value of this variant.
+                        pub const fn as_str(&self) -> &'static str {
+                            match self {
+                                #(Self::#variants => #values,)*
+                            }
+                        }
+                    }
+
+                    impl ::core::fmt::Display for #self_ident {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                            f.write_str(self.as_str())
+                        }
+                    }
+
+                    impl ::core::str::FromStr for #self_ident {
+                        type Err = #runtime_path::UnknownVariant;
+
+                        fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                            match s {
+                                #(#values => ::core::result::Result::Ok(Self::#variants),)*
+                                _ => ::core::result::Result::Err(#runtime_path::UnknownVariant),
+                            }
+                        }
+                    }
+                }
+            }
+
+            TomlValue::Flags(flags) => {
+                let self_ident = key.to_type_ident(naming);
+                let flag_idents = flags
+                    .iter()
+                    .map(|f| f.to_variable_ident())
+                    .collect::<Vec<_>>();
+
+                if let Some(msg) = collision(key, "flag", flags.iter().zip(&flag_idents)) {
+                    return quote! { compile_error!(#msg); };
+                }
+                let bits = (0..flags.len()).map(|i| 1u32 << i);
+                let all_bits = flags
+                    .iter()
+                    .enumerate()
+                    .fold(0u32, |acc, (i, _)| acc | 1 << i);
+                let derives = derive_attrs
+                    .iter()
+                    .map(|attr| quote! { #attr })
+                    .collect::<pm2::TokenStream>();
+
+                quote! {
+                    #[derive(Clone, Copy, Debug)]
+                    #derives
+                    pub struct #self_ident(u32);
 
-                let map_field_ident = MAP_FIELD.to_module_ident();
+                    impl #self_ident {
+                        #(
+                            #[doc = concat!("`", #flags, "`")]
+                            pub const #flag_idents: Self = Self(#bits);
+                        )*
+
+                        /// No flags set.
+                        pub const fn empty() -> Self {
+                            Self(0)
+                        }
+
+                        /// All flags set.
+                        pub const fn all() -> Self {
+                            Self(#all_bits)
+                        }
+
+                        /// The raw bits of these flags.
+                        pub const fn bits(&self) -> u32 {
+                            self.0
+                        }
+
+                        /// Create flags from raw bits, if they only contain known flags.
+                        pub const fn from_bits(bits: u32) -> Option<Self> {
+                            match bits & !#all_bits {
+                                0 => Some(Self(bits)),
+                                _ => None,
+                            }
+                        }
+
+                        /// Returns true if no flags are set.
+                        pub const fn is_empty(&self) -> bool {
+                            self.0 == 0
+                        }
+
+                        /// Returns true if all flags in `other` are set.
+                        pub const fn contains(&self, other: Self) -> bool {
+                            self.0 & other.0 == other.0
+                        }
+
+                        /// Returns true if any flag in `other` is set.
+                        pub const fn intersects(&self, other: Self) -> bool {
+                            self.0 & other.0 != 0
+                        }
+
+                        /// Flags set in either `self` or `other`.
+                        pub const fn union(self, other: Self) -> Self {
+                            Self(self.0 | other.0)
+                        }
+
+                        /// Flags set in both `self` and `other`.
+                        pub const fn intersection(self, other: Self) -> Self {
+                            Self(self.0 & other.0)
+                        }
+
+                        /// Flags set in `self` but not in `other`.
+                        pub const fn difference(self, other: Self) -> Self {
+                            Self(self.0 & !other.0)
+                        }
+
+                        /// Flags not set in `self`.
+                        pub const fn complement(self) -> Self {
+                            Self(!self.0 & #all_bits)
+                        }
+                    }
+                }
+            }
+
+            // the type is user-defined, only types of its values are defined
+            TomlValue::Mapped { table, .. } => {
+                let self_mod = key.to_type_module_ident();
+                let inner_definitions = match table.as_ref() {
+                    TomlValue::Table(tab) => shared.in_module(&self_mod, |shared| {
+                        tab.iter()
+                            .map(|(k, v)| v.define(k, derive_attrs, runtime_path, naming, shared))
+                            .collect::<pm2::TokenStream>()
+                    }),
+                    _ => unimplemented!("mapped values are tables"),
+                };
+
+                quote! {
+                    #[allow(non_snake_case)]
+                    pub mod #self_mod {
+                        #inner_definitions
+                    }
+                }
+            }
+            TomlValue::TableMap { keys, value_type } => {
+                let self_ident = key.to_type_ident(naming);
+                let self_mod = key.to_type_module_ident();
+                let value_key = map_value_key(key, keys);
+                let all_field_type =
+                    value_type.ty(&value_key, Some(&self_mod), runtime_path, naming);
+
+                let fields = keys
+                    .iter()
+                    .map(|k| k.to_module_ident(naming))
+                    .collect::<Vec<_>>();
+                let private_fields =
+                    [MAP_FIELD, SORTED_FIELD].map(|field| Ident::new(field, Span::call_site()));
+                if let Some(msg) = collision(key, "field", keys.iter().zip(&fields))
+                    .or_else(|| private_collision(key, keys.iter().zip(&fields), &private_fields))
+                {
+                    let msg = format!("{}, {}", msg, COLLISION_HINT);
+                    return quote! { compile_error!(#msg); };
+                }
+
+                let key_ident = format!("{}_key", key).to_type_ident(naming);
+                let key_doc = format!("Keys of [`{}`].", self_ident);
+                let variants = keys
+                    .iter()
+                    .map(|k| k.to_variant_ident(naming))
+                    .collect::<Vec<_>>();
+                if let Some(msg) = collision(key, "variant", keys.iter().zip(&variants)) {
+                    let msg = format!("{}, {}", msg, COLLISION_HINT);
+                    return quote! { compile_error!(#msg); };
+                }
+
+                let sorted_field_ident = Ident::new(SORTED_FIELD, Span::call_site());
+                let sorted_type = quote! {#runtime_path::ConstMap<#all_field_type>};
+                let map_field_ident = Ident::new(MAP_FIELD, Span::call_site());
                 let phf_map_type = quote! {#runtime_path::PhfMap<&'static str, #all_field_type>};
 
-                // final map field type
-                let map_field = quote! {
-                    #map_field_ident: &'static #phf_map_type
+                // private fields that follow the keys, the phf map only exists with its feature
+                let private_fields = match cfg!(feature = "phf") {
+                    true => vec![
+                        quote! { #map_field_ident: &'static #phf_map_type },
+                        quote! { #sorted_field_ident: #sorted_type },
+                    ],
+                    false => vec![quote! { #sorted_field_ident: #sorted_type }],
+                };
+                // `map()` only exists with the phf feature, `const_map()` is always available
+                let map_method = match cfg!(feature = "phf") {
+                    true => quote! {
+                        pub const fn map(&'static self) -> &'static #phf_map_type {
+                            self.#map_field_ident
+                        }
+                    },
+                    false => quote! {},
                 };
 
                 let constructor_fields = keys
                     .iter()
                     .map(|k| {
-                        let field_ident = k.to_module_ident();
+                        let field_ident = k.to_module_ident(naming);
                         quote! {
                             #field_ident: #all_field_type
                         }
@@ -624,12 +2142,12 @@ impl TomlValue {
                     .map(|k| {
                         quote! {pub #k}
                     })
-                    .chain([map_field.clone()])
+                    .chain(private_fields.clone())
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
                 let constructor_fields = constructor_fields
                     .into_iter()
-                    .chain([map_field])
+                    .chain(private_fields)
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
                 let derives = derive_attrs
@@ -637,24 +2155,31 @@ impl TomlValue {
                     .map(|attr| quote! { #attr })
                     .collect::<pm2::TokenStream>();
 
+                let private_idents = match cfg!(feature = "phf") {
+                    true => vec![map_field_ident, sorted_field_ident.clone()],
+                    false => vec![sorted_field_ident.clone()],
+                };
                 let shorthand_init_fields = keys
                     .iter()
-                    .map(|k| k.to_module_ident().to_token_stream())
-                    .chain([map_field_ident.to_token_stream()])
+                    .map(|k| k.to_module_ident(naming).to_token_stream())
+                    .chain(private_idents.iter().map(|i| i.to_token_stream()))
                     .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
 
-                let inner_definitions = value_type.definition(first, derive_attrs, runtime_path);
+                let inner_definitions = shared.in_module(&self_mod, |shared| {
+                    value_type.define(&value_key, derive_attrs, runtime_path, naming, shared)
+                });
 
                 quote! {
                     #[derive(Clone, Copy, Debug)]
                     #derives
+                    #[allow(non_snake_case)]
                     pub struct #self_ident {
                         #struct_fields
                     }
 
                     impl #self_ident {
                         #[doc(hidden)]
-                        #[allow(clippy::too_many_arguments)]
+                        #[allow(clippy::too_many_arguments, non_snake_case)]
                         pub const fn new(
                             #constructor_fields
                         ) -> Self {
@@ -663,11 +2188,184 @@ impl TomlValue {
                             }
                         }
 
-                        pub const fn map(&'static self) -> &'static #phf_map_type {
-                            self.#map_field_ident
+                        #map_method
+
+                        /// Entries sorted by key, which can be searched in const contexts.
+                        pub const fn const_map(&self) -> #sorted_type {
+                            self.#sorted_field_ident
+                        }
+
+                        /// The value of a key.
+                        pub const fn get(&self, key: #key_ident) -> &#all_field_type {
+                            match key {
+                                #(#key_ident::#variants => &self.#fields,)*
+                            }
+                        }
+                    }
+
+                    #[doc = #key_doc]
+                    #[derive(Clone, Copy, Debug)]
+                    #derives
+                    pub enum #key_ident {
+                        #(#variants,)*
+                    }
+
+                    impl #key_ident {
+                        /// All keys, in the order they are declared.
+                        pub const ALL: &'static [Self] = &[#(Self::#variants,)*];
+
+                        /// The key as it is written in the TOML file.
+                        pub const fn as_str(&self) -> &'static str {
+                            match self {
+                                #(Self::#variants => #keys,)*
+                            }
                         }
                     }
 
+                    impl ::core::fmt::Display for #key_ident {
+                        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                            f.write_str(self.as_str())
+                        }
+                    }
+
+                    impl ::core::str::FromStr for #key_ident {
+                        type Err = #runtime_path::UnknownVariant;
+
+                        fn from_str(s: &str) -> ::core::result::Result<Self, Self::Err> {
+                            match s {
+                                #(#keys => ::core::result::Result::Ok(Self::#variants),)*
+                                _ => ::core::result::Result::Err(#runtime_path::UnknownVariant),
+                            }
+                        }
+                    }
+
+                    #[allow(non_snake_case)]
+                    pub mod #self_mod {
+                        #inner_definitions
+                    }
+                }
+            }
+            TomlValue::Union(members) => {
+                let self_ident = key.to_item_type_ident(naming);
+
+                let variants = members
+                    .iter()
+                    .map(|m| {
+                        let variant_ident = MemberKind::of(m).variant_ident();
+                        let variant_type =
+                            m.ty(&union_member_key(key, m), None, runtime_path, naming);
+
+                        quote! {
+                            #variant_ident(#variant_type)
+                        }
+                    })
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
+
+                let inner_definitions = members
+                    .iter()
+                    .map(|m| {
+                        m.define(
+                            &union_member_key(key, m),
+                            derive_attrs,
+                            runtime_path,
+                            naming,
+                            shared,
+                        )
+                    })
+                    .collect::<pm2::TokenStream>();
+
+                let derives = derive_attrs
+                    .iter()
+                    .map(|attr| quote! { #attr })
+                    .collect::<pm2::TokenStream>();
+
+                quote! {
+                    #[derive(Clone, Copy, Debug)]
+                    #derives
+                    pub enum #self_ident {
+                        #variants
+                    }
+
+                    #inner_definitions
+                }
+            }
+            TomlValue::Tagged { tag, variants } => {
+                let self_ident = key.to_type_ident(naming);
+                let self_mod = key.to_type_module_ident();
+                let tag_fn = tag.to_module_ident(naming);
+
+                let variant_idents = variants
+                    .keys()
+                    .map(|name| (name.to_variant_ident(naming), name.to_type_module_ident()))
+                    .collect::<Vec<_>>();
+                let variant_collision = collision(
+                    key,
+                    "variant",
+                    variants.keys().zip(variant_idents.iter().map(|(ty, _)| ty)),
+                )
+                .or_else(|| {
+                    collision(
+                        key,
+                        "module",
+                        variants.keys().zip(variant_idents.iter().map(|(_, m)| m)),
+                    )
+                });
+                if let Some(msg) = variant_collision {
+                    return quote! { compile_error!(#msg); };
+                }
+
+                let enum_variants = variants
+                    .iter()
+                    .map(|(name, v)| {
+                        let variant_ident = name.to_variant_ident(naming);
+                        let variant_type = v.ty(name, Some(&self_mod), runtime_path, naming);
+
+                        quote! {
+                            #variant_ident(#variant_type)
+                        }
+                    })
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
+
+                let tag_arms = variants
+                    .keys()
+                    .map(|name| {
+                        let variant_ident = name.to_variant_ident(naming);
+
+                        quote! {
+                            Self::#variant_ident(_) => #name
+                        }
+                    })
+                    .collect::<Punctuated<pm2::TokenStream, syn::Token![,]>>();
+
+                let inner_definitions = shared.in_module(&self_mod, |shared| {
+                    variants
+                        .iter()
+                        .map(|(name, v)| v.define(name, derive_attrs, runtime_path, naming, shared))
+                        .collect::<pm2::TokenStream>()
+                });
+
+                let derives = derive_attrs
+                    .iter()
+                    .map(|attr| quote! { #attr })
+                    .collect::<pm2::TokenStream>();
+
+                quote! {
+                    #[derive(Clone, Copy, Debug)]
+                    #derives
+                    pub enum #self_ident {
+                        #enum_variants
+                    }
+
+                    impl #self_ident {
+                        /// Value of the tag that selects this variant.
+                        pub const fn #tag_fn(&self) -> &'static str {
+                            match self {
+                                #tag_arms
+                            }
+                        }
+                    }
+
+                    #[allow(non_snake_case)]
                     pub mod #self_mod {
                         #inner_definitions
                     }
@@ -677,6 +2375,198 @@ impl TomlValue {
     }
 }
 
+impl MemberKind {
+    /// Kind of a [TomlValue::Union] member that a value belongs to.
+    pub fn of(value: &TomlValue) -> Self {
+        value.member_kind(&MacroOptions::default())
+    }
+
+    /// Kind of a [TomlValue::Union] member that an actual toml value belongs to.
+    pub fn of_value(value: &toml::Value) -> Self {
+        match value {
+            toml::Value::String(_) => Self::String,
+            toml::Value::Integer(_) => Self::Integer,
+            toml::Value::Float(_) => Self::Float,
+            toml::Value::Boolean(_) => Self::Boolean,
+            toml::Value::Datetime(_) => Self::Datetime,
+            toml::Value::Array(_) => Self::Array,
+            toml::Value::Table(_) => Self::Table,
+        }
+    }
+
+    /// Enum variant this member is generated as.
+    pub fn variant_ident(&self) -> syn::Ident {
+        let variant = match self {
+            Self::String => "String",
+            Self::Integer => "Integer",
+            Self::Float => "Float",
+            Self::Boolean => "Boolean",
+            Self::Datetime => "Datetime",
+            Self::Array => "Array",
+            Self::Table => "Table",
+        };
+
+        syn::Ident::new(variant, Span::call_site())
+    }
+}
+
+/// Key that types inside a [TomlValue::Union] member inherit.
+///
+/// Array members use a separate key, so that tables inside them do not conflict with table members.
+pub fn union_member_key(key: &str, member: &TomlValue) -> String {
+    match member {
+        TomlValue::Array(_) | TomlValue::FixedArray { .. } | TomlValue::Tuple(_) => {
+            format!("{}_array", key)
+        }
+        _ => key.to_string(),
+    }
+}
+
+/// Returns a compile error if distinct keys in a table generate the same field, type or module,
+/// e.g. `a-b` and `a_b`.
+fn key_collision(
+    key: &str,
+    table: &IndexMap<String, TomlValue>,
+    naming: &Naming,
+) -> Option<pm2::TokenStream> {
+    let fields = table
+        .keys()
+        .map(|k| (k, k.to_module_ident(naming)))
+        .collect::<Vec<_>>();
+    let types = table
+        .iter()
+        .flat_map(|(k, v)| v.defined_types(k, naming).into_iter().map(move |i| (k, i)))
+        .collect::<Vec<_>>();
+    let modules = table
+        .iter()
+        .flat_map(|(k, v)| v.defined_modules(k).into_iter().map(move |i| (k, i)))
+        .collect::<Vec<_>>();
+
+    let private_fields = table
+        .iter()
+        .filter_map(|(k, v)| match v {
+            TomlValue::Indexed { key: by, .. } => Some(index_idents(k, by, naming).1),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let msg = collision(key, "field", fields.iter().map(|(k, i)| (*k, i)))
+        .or_else(|| collision(key, "type", types.iter().map(|(k, i)| (*k, i))))
+        .or_else(|| collision(key, "module", modules.iter().map(|(k, i)| (*k, i))))
+        .or_else(|| private_collision(key, fields.iter().map(|(k, i)| (*k, i)), &private_fields))?;
+    let msg = format!("{}, {}", msg, COLLISION_HINT);
+
+    Some(quote! { compile_error!(#msg); })
+}
+
+/// Returns an error message if a key generates the same field as a private field of its table.
+///
+/// Keys are only kept in uppercase with `#[naming(fields = original)]`.
+fn private_collision<'a>(
+    key: &str,
+    fields: impl IntoIterator<Item = (&'a String, &'a Ident)>,
+    private_fields: &[Ident],
+) -> Option<String> {
+    fields
+        .into_iter()
+        .find(|(_, ident)| private_fields.iter().any(|p| *p == ident.unraw()))
+        .map(|(name, ident)| {
+            format!(
+                "{}: \"{}\" generates the field `{}`, which is a private field of the table",
+                key,
+                name,
+                ident.unraw()
+            )
+        })
+}
+
+/// Method and private field of an indexed array, e.g. `servers_by_name` and `SERVERS_BY_NAME`.
+fn index_idents(key: &str, by: &str, naming: &Naming) -> (Ident, Ident) {
+    let method = format!("{}_by_{}", key, by).to_module_ident(naming);
+    let field = Ident::new(
+        &method.unraw().to_string().to_uppercase(),
+        Span::call_site(),
+    );
+
+    (method, field)
+}
+
+/// Returns an error message if distinct names generate the same identifier.
+fn collision<'a>(
+    key: &str,
+    kind: &str,
+    names: impl IntoIterator<Item = (&'a String, &'a Ident)>,
+) -> Option<String> {
+    let mut seen = IndexMap::<String, &String>::new();
+
+    for (name, ident) in names {
+        let ident = ident.unraw().to_string();
+
+        match seen.get(&ident) {
+            Some(other) => {
+                return Some(format!(
+                    "{}: \"{}\" and \"{}\" both generate the {} `{}`",
+                    key, other, name, kind, ident
+                ))
+            }
+            None => {
+                seen.insert(ident, name);
+            }
+        }
+    }
+
+    None
+}
+
+/// Merge two lists of values, keeping the order they are found in.
+fn merge_values(values: &[String], other: &[String]) -> Vec<String> {
+    let mut merged = values.to_vec();
+    for value in other {
+        if !merged.contains(value) {
+            merged.push(value.clone());
+        }
+    }
+
+    merged
+}
+
+/// Key that types inside the value of a [TomlValue::TableMap] inherit, e.g. `servers_value`.
+///
+/// Types are named after the table map, so that reordering its keys does not rename them.
+/// Maps with a single key use that key, like tables do.
+pub fn map_value_key(key: &str, keys: &[String]) -> String {
+    match keys {
+        [only] => only.clone(),
+        _ => format!("{}_value", key),
+    }
+}
+
+/// Enum of the keys of a [TomlValue::TableMap], e.g. `ServersKey`.
+fn map_key_type(value: &TomlValue, key: &str, naming: &Naming) -> Option<Ident> {
+    match value {
+        TomlValue::TableMap { .. } => Some(format!("{}_key", key).to_type_ident(naming)),
+        _ => None,
+    }
+}
+
+/// Key that types inside a [TomlValue::Tuple] element inherit.
+pub fn tuple_element_key(key: &str, index: usize) -> String {
+    format!("{}_{}", key, index)
+}
+
+/// Union of two optional array elements.
+fn union_elements(
+    a: Option<&TomlValue>,
+    b: Option<&TomlValue>,
+    options: &MacroOptions,
+) -> Result<Option<TomlValue>, NormalizationError> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.union(b, options).map(Some),
+        (Some(e), None) | (None, Some(e)) => Ok(Some(e.clone())),
+        (None, None) => Ok(None),
+    }
+}
+
 fn date_time_struct_ident(date: bool, time: bool, offset: bool) -> syn::Ident {
     match (date, time, offset) {
         (_, _, true) => syn::Ident::new("OffsetDateTime", Span::call_site()),
@@ -731,7 +2621,7 @@ mod tests {
 
         println!("original: {:#?}", toml_val);
 
-        let normalized = match toml_val.normalize() {
+        let normalized = match toml_val.normalize(&MacroOptions::default()) {
             Ok(n) => n,
             Err(e) => panic!("{}", e),
         };
@@ -749,6 +2639,7 @@ mod tests {
                 "TOP_LEVEL_TABLE",
                 &[],
                 &syn::parse_str("toml_const").expect("valid path"),
+                &Naming::default()
             )
         );
     }
@@ -767,7 +2658,7 @@ mod tests {
 
         let parsed = toml::Table::from_str(toml).expect("must parse");
         let toml_val = TomlValue::from(parsed.clone());
-        match toml_val.normalize() {
+        match toml_val.normalize(&MacroOptions::default()) {
             Ok(n) => {
                 panic!("Normalization should have failed, but succeeded: {:#?}", n);
             }
@@ -797,7 +2688,7 @@ mod tests {
 
         let parsed = toml::Table::from_str(toml).expect("must parse");
         let toml_val = TomlValue::from(parsed.clone());
-        match toml_val.normalize() {
+        match toml_val.normalize(&MacroOptions::default()) {
             Ok(n) => {
                 panic!("Normalization should have failed, but succeeded: {:#?}", n);
             }
@@ -818,15 +2709,485 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_normalize_promote_numbers() {
+        let toml = r#"
+        values = [1, 2.5, 3]
+
+        [[products]]
+        price = 1000
+
+        [[products]]
+        price = 999.99
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let toml_val = TomlValue::from(parsed.clone());
+
+        assert!(toml_val
+            .clone()
+            .normalize(&MacroOptions::default())
+            .is_err());
+
+        let options = MacroOptions {
+            promote_numbers: true,
+            ..Default::default()
+        };
+        let normalized = toml_val.normalize(&options).expect("must normalize");
+
+        let mut og_value = toml::Value::Table(parsed);
+        normalized.normalize_toml(&mut og_value);
+
+        let values = og_value["values"].as_array().unwrap();
+        assert!(values.iter().all(|v| v.is_float()));
+        assert_eq!(og_value["products"][0]["price"].as_float(), Some(1000.0));
+    }
+
+    #[test]
+    fn test_normalize_mixed_arrays() {
+        let toml = r#"
+        args = ["--verbose", 3, { name = "plugin" }, 4]
+
+        [[items]]
+        value = 1
+
+        [[items]]
+        value = "one"
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let toml_val = TomlValue::from(parsed);
+
+        assert!(toml_val
+            .clone()
+            .normalize(&MacroOptions::default())
+            .is_err());
+
+        let options = MacroOptions {
+            mixed_arrays: true,
+            ..Default::default()
+        };
+        let normalized = toml_val.normalize(&options).expect("must normalize");
+
+        let TomlValue::Table(table) = normalized else {
+            panic!("expected a table")
+        };
+
+        let mut args_table = IndexMap::new();
+        args_table.insert("name".to_string(), TomlValue::String);
+        assert_eq!(
+            table["args"],
+            TomlValue::Array(vec![TomlValue::Union(vec![
+                TomlValue::String,
+                TomlValue::Integer,
+                TomlValue::Table(args_table),
+            ])])
+        );
+
+        let mut items_table = IndexMap::new();
+        items_table.insert(
+            "value".to_string(),
+            TomlValue::Union(vec![TomlValue::String, TomlValue::Integer]),
+        );
+        assert_eq!(
+            table["items"],
+            TomlValue::Array(vec![TomlValue::Table(items_table)])
+        );
+    }
+
+    #[test]
+    fn test_normalize_tagged() {
+        let toml = r#"
+        [[listeners]]
+        kind = "http"
+        port = 8080
+        path = "/"
+
+        [[listeners]]
+        kind = "grpc"
+        port = 9090
+
+        [[listeners]]
+        kind = "http"
+        port = 8081
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let options = MacroOptions {
+            tagged: vec![syn::parse_quote! {listeners by kind}],
+            ..Default::default()
+        };
+
+        let toml_val = TomlValue::from_table(&parsed, &options).expect("must convert");
+        let normalized = toml_val.normalize(&options).expect("must normalize");
+
+        let TomlValue::Table(table) = normalized else {
+            panic!("expected a table")
+        };
+        let TomlValue::Array(listeners) = &table["listeners"] else {
+            panic!("expected an array")
+        };
+        let TomlValue::Tagged { tag, variants } = &listeners[0] else {
+            panic!("expected tagged tables")
+        };
+
+        assert_eq!(tag, "kind");
+        assert_eq!(variants.keys().collect::<Vec<_>>(), ["http", "grpc"]);
+
+        let mut http = IndexMap::new();
+        http.insert("port".to_string(), TomlValue::Integer);
+        http.insert("path".to_string(), TomlValue::String);
+        assert_eq!(variants["http"], TomlValue::Table(http));
+
+        let missing_tag = toml::Table::from_str("[[listeners]]\nport = 1").expect("must parse");
+        assert!(TomlValue::from_table(&missing_tag, &options).is_err());
+    }
+
+    #[test]
+    fn test_normalize_enum_values() {
+        let toml = r#"
+        log_level = "debug"
+
+        [[routes]]
+        method = "GET"
+
+        [[routes]]
+        method = "POST"
+
+        [[routes]]
+        method = "GET"
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let options = MacroOptions {
+            enum_values: vec![
+                syn::parse_quote! {log_level = [trace, debug, info]},
+                syn::parse_quote! {routes.method},
+            ],
+            ..Default::default()
+        };
+
+        let toml_val = TomlValue::from_table(&parsed, &options).expect("must convert");
+        let normalized = toml_val.normalize(&options).expect("must normalize");
+
+        let TomlValue::Table(table) = normalized else {
+            panic!("expected a table")
+        };
+        assert_eq!(
+            table["log_level"],
+            TomlValue::Enum(vec!["trace".into(), "debug".into(), "info".into()])
+        );
+
+        let mut route = IndexMap::new();
+        route.insert(
+            "method".to_string(),
+            TomlValue::Enum(vec!["GET".into(), "POST".into()]),
+        );
+        assert_eq!(
+            table["routes"],
+            TomlValue::Array(vec![TomlValue::Table(route)])
+        );
+
+        let typo = toml::Table::from_str("log_level = \"degub\"").expect("must parse");
+        assert!(TomlValue::from_table(&typo, &options).is_err());
+    }
+
+    #[test]
+    fn test_normalize_flags() {
+        let toml = r#"
+        [[users]]
+        capabilities = ["read"]
+
+        [[users]]
+        capabilities = ["write", "read"]
+
+        [[users]]
+        name = "guest"
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let options = MacroOptions {
+            flags: vec![syn::parse_quote! {users.capabilities}],
+            ..Default::default()
+        };
+
+        let toml_val = TomlValue::from_table(&parsed, &options).expect("must convert");
+        let normalized = toml_val.normalize(&options).expect("must normalize");
+
+        let mut user = IndexMap::new();
+        user.insert(
+            "capabilities".to_string(),
+            TomlValue::Flags(vec!["read".into(), "write".into()]),
+        );
+        user.insert("name".to_string(), TomlValue::String);
+
+        let TomlValue::Table(table) = normalized else {
+            panic!("expected a table")
+        };
+        assert_eq!(
+            table["users"],
+            TomlValue::Array(vec![TomlValue::Table(user)])
+        );
+
+        let options = MacroOptions {
+            flags: vec![syn::parse_quote! {users.capabilities = [read]}],
+            ..Default::default()
+        };
+        assert!(TomlValue::from_table(&parsed, &options).is_err());
+    }
+
+    #[test]
+    fn test_normalize_indexed() {
+        let toml = r#"
+        [[products]]
+        name = "Laptop"
+        price = 1000
+
+        [[products]]
+        name = "Tablet"
+
+        [[discontinued]]
+        name = "Phone"
+        price = 200
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let options = MacroOptions {
+            indexed: vec![syn::parse_quote! {"*" by name}],
+            ..Default::default()
+        };
+
+        let toml_val = TomlValue::from_table(&parsed, &options).expect("must convert");
+        let reduced = toml_val
+            .normalize(&options)
+            .expect("must normalize")
+            .reduce(&options)
+            .expect("must reduce");
+
+        // tables with indexed arrays are not reduced to table maps
+        let TomlValue::Table(table) = reduced else {
+            panic!("expected a table")
+        };
+        let TomlValue::Indexed { key, array } = &table["products"] else {
+            panic!("expected an indexed array")
+        };
+        assert_eq!(key, "name");
+        assert!(matches!(array.as_ref(), TomlValue::Array(elements) if elements.len() == 1));
+        assert_eq!(table["products"], table["discontinued"]);
+
+        let invalid = |toml: &str| {
+            let parsed = toml::Table::from_str(toml).expect("must parse");
+            TomlValue::from_table(&parsed, &options)
+                .expect_err("must not convert")
+                .to_string()
+        };
+        assert!(
+            invalid("[[products]]\nname = \"a\"\n[[products]]\nname = \"a\"")
+                .contains("contains \"name\" = \"a\" more than once")
+        );
+        assert!(invalid("[[products]]\nname = 1").contains("must be a string"));
+        assert!(invalid("[[products]]\nprice = 1").contains("missing the index key"));
+        assert!(invalid("products = [1, 2]").contains("must be tables"));
+    }
+
+    #[test]
+    fn test_reduce_table_maps() {
+        let toml = r#"
+        [settings]
+        retries = 3
+        timeout = 10
+
+        [servers.alpha]
+        host = "a"
+        port = 1
+
+        [servers.beta]
+        host = "b"
+        tls = { cert = "cert.pem" }
+
+        [limits.soft]
+        max = 1
+        [limits.hard]
+        max = "unlimited"
+        "#;
+
+        let parsed = toml::Table::from_str(toml).expect("must parse");
+        let reduce = |options: &MacroOptions| {
+            let TomlValue::Table(table) = TomlValue::from_table(&parsed, options)
+                .expect("must convert")
+                .normalize(options)
+                .expect("must normalize")
+                .reduce(options)?
+            else {
+                panic!("expected a table")
+            };
+            Ok::<_, NormalizationError>(table)
+        };
+
+        let default = reduce(&MacroOptions::default()).expect("must reduce");
+        assert!(matches!(default["settings"], TomlValue::TableMap { .. }));
+        assert!(matches!(default["servers"], TomlValue::Table(_)));
+
+        let options = MacroOptions {
+            table_maps: vec![syn::parse_quote! {servers}],
+            no_table_maps: vec![syn::parse_quote! {settings}],
+            ..Default::default()
+        };
+        let table = reduce(&options).expect("must reduce");
+        assert!(matches!(table["settings"], TomlValue::Table(_)));
+
+        // values of forced table maps are merged, including their inner tables
+        let TomlValue::TableMap { keys, value_type } = &table["servers"] else {
+            panic!("expected a table map")
+        };
+        assert_eq!(keys, &["alpha", "beta"]);
+        let TomlValue::Table(server) = value_type.as_ref() else {
+            panic!("expected a table")
+        };
+        assert_eq!(server.keys().collect::<Vec<_>>(), ["host", "port", "tls"]);
+
+        // values that cannot be merged fail with their path
+        let options = MacroOptions {
+            table_maps: vec![syn::parse_quote! {limits}],
+            ..Default::default()
+        };
+        let error = reduce(&options).expect_err("must not reduce").to_string();
+        assert!(error.contains("limits::hard::max"));
+    }
+
+    #[test]
+    fn test_shared_shapes() {
+        let runtime_path: syn::Path = syn::parse_quote! {toml_const};
+        let table = |toml: &str| TomlValue::from(toml::Table::from_str(toml).expect("must parse"));
+
+        // tables have the same shape in any order
+        let hash = |value: &TomlValue| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (a, b) = (table("x = 1\ny = \"a\""), table("y = \"b\"\nx = 2"));
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(a, table("x = 1\ny = 2"));
+
+        let shared =
+            table("[a.x]\none = 1\ntwo = 2\n[b.x]\none = 3\ntwo = 4\n[c.y]\none = 5\ntwo = 6")
+                .reduce(&MacroOptions::default())
+                .expect("must reduce")
+                .shared_definition("root", &[], &runtime_path, &Naming::default())
+                .to_string();
+        assert!(shared.contains("pub type B = self :: A ;"));
+        // table maps with several keys name their values after the map
+        assert!(!shared.contains("pub type Y ="));
+    }
+
+    #[test]
+    fn test_identifier_collisions() {
+        let runtime_path: syn::Path = syn::parse_quote! {toml_const};
+        let definition = |toml: &str| {
+            let parsed = toml::Table::from_str(toml).expect("must parse");
+            TomlValue::from(parsed)
+                .definition("root", &[], &runtime_path, &Naming::default())
+                .to_string()
+        };
+
+        let fields = definition("a-b = 1\na_b = 2\nc = \"c\"");
+        assert!(fields.contains(r#"\"a-b\" and \"a_b\" both generate the field `a_b`"#));
+
+        let types = definition("[a_b]\nx = 1\n[aB]\ny = 2");
+        assert!(types.contains(r#"\"a_b\" and \"aB\" both generate the type `AB`"#));
+
+        let variants = TomlValue::Enum(vec!["warn-only".into(), "warn_only".into()])
+            .definition("level", &[], &runtime_path, &Naming::default())
+            .to_string();
+        assert!(variants.contains("both generate the variant `WarnOnly`"));
+
+        let distinct = definition("a_b = 1\naB = 2");
+        assert!(!distinct.contains("compile_error"));
+
+        // private fields can only collide with original field names
+        let original = Naming {
+            fields: crate::options::FieldNaming::Original,
+            ..Default::default()
+        };
+        let table_map = TomlValue::TableMap {
+            keys: vec!["SORTED".to_string(), "b".to_string()],
+            value_type: Box::new(TomlValue::Integer),
+        };
+        let private = table_map
+            .definition("ports", &[], &runtime_path, &original)
+            .to_string();
+        assert!(private.contains(r#"ports: \"SORTED\" generates the field `SORTED`"#));
+        let snake_case = table_map
+            .definition("ports", &[], &runtime_path, &Naming::default())
+            .to_string();
+        assert!(!snake_case.contains("compile_error"));
+
+        let mut indexed = IndexMap::new();
+        indexed.insert(
+            "servers".to_string(),
+            TomlValue::Indexed {
+                key: "name".to_string(),
+                array: Box::new(TomlValue::Array(vec![TomlValue::String])),
+            },
+        );
+        indexed.insert("SERVERS_BY_NAME".to_string(), TomlValue::Integer);
+        let private = TomlValue::Table(indexed)
+            .definition("root", &[], &runtime_path, &original)
+            .to_string();
+        assert!(private.contains("generates the field `SERVERS_BY_NAME`"));
+
+        // modules are snake_case with any field naming
+        let parsed = toml::Table::from_str("[apiGateway]\nx = 1\n[APIGateway]\ny = 2").unwrap();
+        let modules = TomlValue::from(parsed)
+            .definition("root", &[], &runtime_path, &original)
+            .to_string();
+        assert!(modules.contains(
+            r#"\"apiGateway\" and \"APIGateway\" both generate the module `apigateway`"#
+        ));
+
+        // key enums and tuple elements are defined next to their value
+        let mut siblings = IndexMap::new();
+        siblings.insert(
+            "servers".to_string(),
+            TomlValue::TableMap {
+                keys: vec!["a".to_string(), "b".to_string()],
+                value_type: Box::new(TomlValue::Integer),
+            },
+        );
+        siblings.insert("servers_key".to_string(), TomlValue::Table(IndexMap::new()));
+        let key_enum = TomlValue::Table(siblings)
+            .definition("root", &[], &runtime_path, &Naming::default())
+            .to_string();
+        assert!(key_enum
+            .contains(r#"\"servers\" and \"servers_key\" both generate the type `ServersKey`"#));
+
+        let mut siblings = IndexMap::new();
+        siblings.insert(
+            "pair".to_string(),
+            TomlValue::Tuple(vec![TomlValue::Integer, TomlValue::Table(IndexMap::new())]),
+        );
+        siblings.insert("pair_1".to_string(), TomlValue::Table(IndexMap::new()));
+        let tuple = TomlValue::Table(siblings)
+            .definition("root", &[], &runtime_path, &Naming::default())
+            .to_string();
+        assert!(tuple.contains(r#"\"pair\" and \"pair_1\" both generate the type `Pair1`"#));
+    }
+
     #[test]
     fn test_show_tablemap_normalize() {
         let normalize_toml = include_str!("../../normalize.toml");
         let parsed = toml::Table::from_str(normalize_toml).expect("must parse");
 
         let toml_val = TomlValue::from(parsed.clone());
-        let normalized = toml_val.normalize().expect("must normalize");
+        let normalized = toml_val
+            .normalize(&MacroOptions::default())
+            .expect("must normalize");
 
-        let reduced = normalized.reduce();
+        let reduced = normalized
+            .reduce(&MacroOptions::default())
+            .expect("must reduce");
         println!("reduced: {:#?}", reduced);
 
         // println!("normalized: {:#?}", normalized);
@@ -0,0 +1,1247 @@
+//! Macro options.
+//!
+//! Options are attributes on a macro input that change how code is generated.
+//! They are consumed by the macro and never forwarded to generated code.
+
+use proc_macro2::{self as pm2, Span};
+use quote::{quote, ToTokens};
+use syn::{ext::IdentExt, parse::Parse, punctuated::Punctuated, spanned::Spanned, LitStr, Token};
+
+use crate::types::{EmbedKind, PathBase, ValueType};
+
+/// Unify integers and floats to floats when normalizing.
+const PROMOTE_NUMBERS: &str = "promote_numbers";
+/// Allow arrays to contain values of distinct types.
+const MIXED_ARRAYS: &str = "mixed_arrays";
+/// Arrays of tables that are split into variants by a discriminator key.
+const TAGGED: &str = "tagged";
+/// Arrays generated as fixed-size arrays or tuples.
+const FIXED_ARRAYS: &str = "fixed_arrays";
+/// Values generated as other types.
+const TY: &str = "ty";
+/// Tables instantiated as user-defined types.
+const MAP: &str = "map";
+/// Strings generated as enums.
+const ENUM_VALUES: &str = "enum_values";
+/// Arrays of strings generated as bitflags.
+const FLAGS: &str = "flags";
+/// Paths to files whose contents are embedded.
+const EMBED: &str = "embed";
+/// Paths that are resolved and checked to exist.
+const PATH: &str = "path";
+/// Keys that are renamed before code is generated.
+const RENAME: &str = "rename";
+/// Keys that generate the same identifiers as earlier keys are suffixed.
+const SUFFIX_COLLISIONS: &str = "suffix_collisions";
+/// Naming conventions for generated fields and types.
+const NAMING: &str = "naming";
+/// One type for each distinct table shape.
+const SHARED_TYPES: &str = "shared_types";
+/// Module that contains all generated definitions.
+const NAMESPACE: &str = "namespace";
+/// Arrays of tables that are indexed by a key.
+const INDEX: &str = "index";
+/// Tables that are always generated as table maps.
+const TABLE_MAP: &str = "table_map";
+/// Tables that are never generated as table maps.
+const NO_TABLE_MAP: &str = "no_table_map";
+
+/// All attribute paths that are consumed as options.
+const OPTION_ATTR_PATHS: &[&str] = &[
+    PROMOTE_NUMBERS,
+    MIXED_ARRAYS,
+    TAGGED,
+    FIXED_ARRAYS,
+    TY,
+    MAP,
+    ENUM_VALUES,
+    FLAGS,
+    EMBED,
+    PATH,
+    RENAME,
+    SUFFIX_COLLISIONS,
+    NAMING,
+    SHARED_TYPES,
+    NAMESPACE,
+    INDEX,
+    TABLE_MAP,
+    NO_TABLE_MAP,
+];
+
+/// Options that apply to a single macro input.
+#[derive(Clone, Debug, Default)]
+pub struct MacroOptions {
+    /// `Integer` and `Float` values found in the same array are unified to `Float`.
+    pub promote_numbers: bool,
+
+    /// Arrays with distinct value types are generated as arrays of enums.
+    pub mixed_arrays: bool,
+
+    /// Arrays of tables that are generated as enums, one variant per tag value.
+    pub tagged: Vec<TaggedArray>,
+
+    /// All arrays are generated as `[T; N]` or tuples, if their length is the same everywhere.
+    pub fixed_arrays: bool,
+
+    /// Arrays at these paths are generated as `[T; N]` or tuples.
+    pub fixed_array_paths: Vec<KeyPattern>,
+
+    /// Values at these paths are generated as other types, including embedded files and paths.
+    pub value_types: Vec<TypeOverride>,
+
+    /// Tables at these paths are instantiated as user-defined types.
+    pub mapped: Vec<MappedTable>,
+
+    /// Strings at these paths are generated as enums.
+    pub enum_values: Vec<ValueSet>,
+
+    /// Arrays of strings at these paths are generated as bitflags.
+    pub flags: Vec<ValueSet>,
+
+    /// Keys at these paths are renamed.
+    pub renamed: Vec<RenamedKey>,
+
+    /// Keys that generate the same identifiers as an earlier key in their table get a numeric suffix,
+    /// e.g. `a-b` becomes `a-b_2` after `a_b`.
+    pub suffix_collisions: bool,
+
+    /// Naming conventions for generated fields and types.
+    pub naming: Naming,
+
+    /// Identical tables, enums and flags at different paths share one type,
+    /// with type aliases at each path.
+    pub shared_types: bool,
+
+    /// All definitions are generated inside this module, and only the root type is re-exported.
+    pub namespace: Option<syn::Ident>,
+
+    /// Arrays of tables that are indexed by the string value of a key.
+    pub indexed: Vec<IndexedArray>,
+
+    /// Tables at these paths are table maps, even if their values have different shapes.
+    pub table_maps: Vec<KeyPattern>,
+
+    /// Tables at these paths are never table maps. Takes precedence over [Self::table_maps].
+    pub no_table_maps: Vec<KeyPattern>,
+}
+
+/// `#[naming(fields = original, types = upper_camel_case, type_prefix = Config)]`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Naming {
+    /// Convention for fields and modules
+    pub fields: FieldNaming,
+    /// Convention for types and enum variants
+    pub types: TypeNaming,
+    /// Prepended to all generated types
+    pub type_prefix: String,
+    /// Appended to all generated types
+    pub type_suffix: String,
+}
+
+/// How field and module identifiers are generated from keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FieldNaming {
+    /// `snake_case`, e.g. `HTTPServer` becomes `httpserver`
+    #[default]
+    SnakeCase,
+    /// Keys that are valid identifiers are kept as they are, others are `snake_case`
+    Original,
+}
+
+/// How type and enum variant identifiers are generated from keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TypeNaming {
+    /// Each capital letter starts a word, e.g. `HTTPServer` stays `HTTPServer`
+    #[default]
+    PascalCase,
+    /// Acronyms are a single word, e.g. `HTTPServer` becomes `HttpServer`
+    UpperCamelCase,
+}
+
+/// A single `name = value` item in [Naming].
+enum NamingItem {
+    Fields(FieldNaming),
+    Types(TypeNaming),
+    TypePrefix(String),
+    TypeSuffix(String),
+}
+
+/// A pattern that matches key paths, e.g. `"servers.*.port"` or `listeners`.
+///
+/// Segments are separated by `.` and can be quoted like TOML keys.
+/// A `*` segment matches any single key, and `*` within a segment matches any characters,
+/// e.g. `"*_timeout"`. A `**` segment matches any number of keys, e.g. `"**.*_size"`.
+/// Array elements share the key path of the array they are in.
+#[derive(Clone, Debug)]
+pub struct KeyPattern {
+    segments: Vec<Segment>,
+    span: Span,
+}
+
+/// A single segment in a [KeyPattern].
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Wildcard,
+    /// A key with `*` wildcards, e.g. `*_timeout`
+    Glob(String),
+    /// `**`, any number of keys
+    Recursive,
+}
+
+/// `#[tagged(listeners by kind)]`
+#[derive(Clone, Debug)]
+pub struct TaggedArray {
+    /// Path to the array of tables
+    pub path: KeyPattern,
+    /// Discriminator key inside each table
+    pub tag: String,
+}
+
+/// `#[index(products by name)]`
+#[derive(Clone, Debug)]
+pub struct IndexedArray {
+    /// Path to the array of tables
+    pub path: KeyPattern,
+    /// Key inside each table with a unique string value
+    pub key: String,
+}
+
+/// `#[ty("server.port" = u16)]`
+#[derive(Clone, Debug)]
+pub struct TypeOverride {
+    /// Path to the value, or an array of values
+    pub path: KeyPattern,
+    /// Type that the value is generated as
+    pub ty: ValueType,
+}
+
+/// `#[embed(logo, "templates.*" = str)]`, a [TypeOverride] for file contents.
+///
+/// Files are embedded as bytes unless declared as `str`.
+struct EmbeddedFile(TypeOverride);
+
+/// `#[path(assets, "plugins.*" = manifest)]`, a [TypeOverride] for paths.
+///
+/// Paths are relative to the TOML file unless declared as `manifest`.
+struct ResolvedPath(TypeOverride);
+
+/// `#[rename("'weird key'" = field)]`
+#[derive(Clone, Debug)]
+pub struct RenamedKey {
+    /// Path to the key
+    pub path: KeyPattern,
+    /// Key that replaces the last key in the path
+    pub name: String,
+}
+
+/// A set of allowed strings, for `#[enum_values]` and `#[flags]`.
+///
+/// `log_level = [debug, info]`, or `log_level` to infer values from the file.
+#[derive(Clone, Debug)]
+pub struct ValueSet {
+    /// Path to the string, or an array of strings
+    pub path: KeyPattern,
+    /// Allowed values, in order. Values are inferred from the file if not set.
+    pub values: Option<Vec<String>>,
+}
+
+/// `#[map("servers.*" = crate::ServerConfig)]`
+#[derive(Clone, Debug)]
+pub struct MappedTable {
+    /// Path to the table, or an array of tables
+    pub path: KeyPattern,
+    /// Type that the table is instantiated as
+    pub target: MapTarget,
+}
+
+/// A user-defined type that tables are instantiated as.
+///
+/// Paths are stored as token strings, so that schemas can be compared.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MapTarget {
+    /// Type path, e.g. `crate::ServerConfig`
+    ty: String,
+    init: MapInit,
+}
+
+/// How a [MapTarget] is constructed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MapInit {
+    /// `Type`: a struct literal with every key as a field
+    Literal,
+    /// `Type { a, b }`: a struct literal with declared fields
+    Fields(Vec<String>),
+    /// `Type::new(a, b)`: a constructor call with keys as arguments, in order
+    Constructor { func: String, args: Vec<String> },
+}
+
+impl MacroOptions {
+    /// Returns true if the attribute is a macro option.
+    pub fn is_option_attr(attr: &syn::Attribute) -> bool {
+        OPTION_ATTR_PATHS
+            .iter()
+            .any(|path| attr.path().is_ident(path))
+    }
+
+    /// Collect all options from a list of attributes. Non-option attributes are ignored.
+    pub fn from_attrs(attrs: &[syn::Attribute]) -> Result<Self, syn::Error> {
+        let mut options = Self::default();
+
+        for attr in attrs.iter().filter(|a| Self::is_option_attr(a)) {
+            if attr.path().is_ident(PROMOTE_NUMBERS) {
+                options.promote_numbers = parse_flag(attr, PROMOTE_NUMBERS)?;
+            } else if attr.path().is_ident(MIXED_ARRAYS) {
+                options.mixed_arrays = parse_flag(attr, MIXED_ARRAYS)?;
+            } else if attr.path().is_ident(TAGGED) {
+                options.tagged.extend(parse_list::<TaggedArray>(attr)?);
+            } else if attr.path().is_ident(FIXED_ARRAYS) {
+                match &attr.meta {
+                    syn::Meta::Path(_) => options.fixed_arrays = true,
+                    _ => options
+                        .fixed_array_paths
+                        .extend(parse_list::<KeyPattern>(attr)?),
+                }
+            } else if attr.path().is_ident(TY) {
+                options
+                    .value_types
+                    .extend(parse_list::<TypeOverride>(attr)?);
+            } else if attr.path().is_ident(MAP) {
+                options.mapped.extend(parse_list::<MappedTable>(attr)?);
+            } else if attr.path().is_ident(ENUM_VALUES) {
+                options.enum_values.extend(parse_list::<ValueSet>(attr)?);
+            } else if attr.path().is_ident(FLAGS) {
+                options.flags.extend(parse_list::<ValueSet>(attr)?);
+            } else if attr.path().is_ident(EMBED) {
+                options
+                    .value_types
+                    .extend(parse_list::<EmbeddedFile>(attr)?.into_iter().map(|e| e.0));
+            } else if attr.path().is_ident(PATH) {
+                options
+                    .value_types
+                    .extend(parse_list::<ResolvedPath>(attr)?.into_iter().map(|p| p.0));
+            } else if attr.path().is_ident(RENAME) {
+                options.renamed.extend(parse_list::<RenamedKey>(attr)?);
+            } else if attr.path().is_ident(SUFFIX_COLLISIONS) {
+                options.suffix_collisions = parse_flag(attr, SUFFIX_COLLISIONS)?;
+            } else if attr.path().is_ident(SHARED_TYPES) {
+                options.shared_types = parse_flag(attr, SHARED_TYPES)?;
+            } else if attr.path().is_ident(TABLE_MAP) {
+                options.table_maps.extend(parse_list::<KeyPattern>(attr)?);
+            } else if attr.path().is_ident(NO_TABLE_MAP) {
+                options
+                    .no_table_maps
+                    .extend(parse_list::<KeyPattern>(attr)?);
+            } else if attr.path().is_ident(INDEX) {
+                options.indexed.extend(parse_list::<IndexedArray>(attr)?);
+            } else if attr.path().is_ident(NAMESPACE) {
+                options.namespace = Some(attr.parse_args()?);
+            } else if attr.path().is_ident(NAMING) {
+                for item in parse_list::<NamingItem>(attr)? {
+                    match item {
+                        NamingItem::Fields(fields) => options.naming.fields = fields,
+                        NamingItem::Types(types) => options.naming.types = types,
+                        NamingItem::TypePrefix(prefix) => options.naming.type_prefix = prefix,
+                        NamingItem::TypeSuffix(suffix) => options.naming.type_suffix = suffix,
+                    }
+                }
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Returns true if the array at a key path is generated with a fixed size.
+    pub fn is_fixed_array(&self, path: &[String]) -> bool {
+        self.fixed_arrays || self.fixed_array_paths.iter().any(|p| p.matches(path))
+    }
+
+    /// Returns the tagged array option that applies to a key path, if any.
+    pub fn tagged_array(&self, path: &[String]) -> Option<&TaggedArray> {
+        self.tagged.iter().find(|t| t.path.matches(path))
+    }
+
+    /// Returns `Some(true)` if the table at a key path is always a table map,
+    /// `Some(false)` if it never is, or `None` if it is reduced as usual.
+    pub fn table_map(&self, path: &[String]) -> Option<bool> {
+        match (
+            self.no_table_maps.iter().any(|p| p.matches(path)),
+            self.table_maps.iter().any(|p| p.matches(path)),
+        ) {
+            (true, _) => Some(false),
+            (false, true) => Some(true),
+            (false, false) => None,
+        }
+    }
+
+    /// Returns the index option that applies to a key path, if any.
+    pub fn indexed_array(&self, path: &[String]) -> Option<&IndexedArray> {
+        self.indexed.iter().find(|i| i.path.matches(path))
+    }
+
+    /// Returns the type override that applies to a key path, if any.
+    pub fn value_type(&self, path: &[String]) -> Option<&TypeOverride> {
+        self.value_types.iter().find(|t| t.path.matches(path))
+    }
+
+    /// Returns the enum values that apply to a key path, if any.
+    pub fn enum_values(&self, path: &[String]) -> Option<&ValueSet> {
+        self.enum_values.iter().find(|e| e.path.matches(path))
+    }
+
+    /// Returns the flags that apply to an array at a key path, if any.
+    pub fn flags(&self, path: &[String]) -> Option<&ValueSet> {
+        self.flags.iter().find(|f| f.path.matches(path))
+    }
+
+    /// Returns the rename that applies to a key path, if any.
+    pub fn renamed_key(&self, path: &[String]) -> Option<&RenamedKey> {
+        self.renamed.iter().find(|r| r.path.matches(path))
+    }
+
+    /// Returns the user-defined type that a table at a key path is instantiated as, if any.
+    pub fn mapped_table(&self, path: &[String]) -> Option<&MappedTable> {
+        self.mapped.iter().find(|m| m.path.matches(path))
+    }
+}
+
+impl MapTarget {
+    /// Type path of the target.
+    pub fn ty(&self) -> pm2::TokenStream {
+        self.ty.parse().expect("type path was parsed from tokens")
+    }
+
+    /// Keys that are instantiated, in order. All keys are used for struct literals.
+    pub fn init(&self) -> &MapInit {
+        &self.init
+    }
+
+    /// Check that the keys of a table match the declared fields or arguments.
+    ///
+    /// Struct literals without declared fields are checked by [MapTarget::field_check] instead.
+    pub fn check_keys<'a>(&self, keys: impl IntoIterator<Item = &'a String>) -> Result<(), String> {
+        let declared = match &self.init {
+            MapInit::Literal => return Ok(()),
+            MapInit::Fields(fields) => fields,
+            MapInit::Constructor { args, .. } => args,
+        };
+        let keys = keys.into_iter().collect::<Vec<_>>();
+
+        let list = |names: Vec<&String>| {
+            names
+                .iter()
+                .map(|n| format!("`{}`", n))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let missing = declared
+            .iter()
+            .filter(|d| !keys.contains(d))
+            .collect::<Vec<_>>();
+        let extra = keys
+            .iter()
+            .copied()
+            .filter(|k| !declared.contains(k))
+            .collect::<Vec<_>>();
+
+        let mut errors = Vec::new();
+        if !missing.is_empty() {
+            errors.push(format!("missing keys {}", list(missing)));
+        }
+        if !extra.is_empty() {
+            errors.push(format!("extra keys {}", list(extra)));
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(format!("keys do not match {}: {}", self, errors.join(", "))),
+        }
+    }
+
+    /// A constant that checks the keys of a table against the fields of a struct literal target
+    /// without declared fields, which are only known to the compiler.
+    ///
+    /// It fails with the same error as [MapTarget::check_keys].
+    pub fn field_check<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a String>,
+        path: &[String],
+        runtime_path: &syn::Path,
+    ) -> Option<pm2::TokenStream> {
+        if self.init != MapInit::Literal {
+            return None;
+        }
+
+        let context = format!("{}: keys do not match {}", path.join("."), self);
+        let keys = keys.into_iter();
+        let ty = self.ty();
+
+        Some(quote! {
+            const _: () = #runtime_path::__private::check_keys(
+                #context,
+                &[#(#keys),*],
+                <#ty as #runtime_path::__private::Fields>::FIELDS,
+            );
+        })
+    }
+}
+
+impl MapInit {
+    /// Constructor function path, for [MapInit::Constructor].
+    pub fn func(&self) -> Option<pm2::TokenStream> {
+        match self {
+            MapInit::Constructor { func, .. } => {
+                Some(func.parse().expect("function path was parsed from tokens"))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MapTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.ty.replace(' ', ""))
+    }
+}
+
+impl KeyPattern {
+    /// Returns true if the pattern matches a key path exactly.
+    pub fn matches(&self, path: &[String]) -> bool {
+        Self::matches_segments(&self.segments, path)
+    }
+
+    fn matches_segments(segments: &[Segment], path: &[String]) -> bool {
+        match (segments.split_first(), path.split_first()) {
+            (None, None) => true,
+            (Some((Segment::Recursive, rest)), _) => {
+                Self::matches_segments(rest, path)
+                    || (!path.is_empty() && Self::matches_segments(segments, &path[1..]))
+            }
+            (Some((segment, rest)), Some((key, path))) => {
+                let matches = match segment {
+                    Segment::Key(k) => k == key,
+                    Segment::Wildcard => true,
+                    Segment::Glob(glob) => glob_matches(glob, key),
+                    Segment::Recursive => unreachable!("matched above"),
+                };
+
+                matches && Self::matches_segments(rest, path)
+            }
+            _ => false,
+        }
+    }
+
+    /// Span of the pattern in the macro input.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Parse a dotted key pattern from a string.
+    fn from_str(pattern: &str, span: Span) -> Result<Self, syn::Error> {
+        let mut segments = Vec::new();
+        let mut chars = pattern.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            let segment = match chars.peek() {
+                Some(quote @ ('"' | '\'')) => {
+                    let quote = *quote;
+                    chars.next();
+
+                    let mut key = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(c) if c == quote => break,
+                            Some(c) => key.push(c),
+                            None => {
+                                return Err(syn::Error::new(
+                                    span,
+                                    format!("unterminated quoted key in \"{}\"", pattern),
+                                ))
+                            }
+                        }
+                    }
+
+                    Segment::Key(key)
+                }
+                _ => {
+                    let mut key = String::new();
+                    while let Some(c) = chars.next_if(|c| *c != '.' && !c.is_whitespace()) {
+                        key.push(c);
+                    }
+
+                    match key.as_str() {
+                        "" => {
+                            return Err(syn::Error::new(
+                                span,
+                                format!("empty key in \"{}\", use quotes for empty keys", pattern),
+                            ))
+                        }
+                        "*" => Segment::Wildcard,
+                        "**" => Segment::Recursive,
+                        _ if key.contains('*') => Segment::Glob(key),
+                        _ => Segment::Key(key),
+                    }
+                }
+            };
+            segments.push(segment);
+
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            match chars.next() {
+                Some('.') => continue,
+                None => break,
+                Some(c) => {
+                    return Err(syn::Error::new(
+                        span,
+                        format!("unexpected character '{}' in \"{}\"", c, pattern),
+                    ))
+                }
+            }
+        }
+
+        Ok(Self { segments, span })
+    }
+}
+
+impl std::fmt::Display for KeyPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let segments = self
+            .segments
+            .iter()
+            .map(|s| match s {
+                Segment::Key(k) | Segment::Glob(k) => k.as_str(),
+                Segment::Wildcard => "*",
+                Segment::Recursive => "**",
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+
+        write!(f, "{}", segments)
+    }
+}
+
+impl Parse for KeyPattern {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            let lit: LitStr = input.parse()?;
+            return Self::from_str(&lit.value(), lit.span());
+        }
+
+        // dotted identifiers, e.g. `servers.*.port`
+        let span = input.span();
+        let mut segments = Vec::new();
+        loop {
+            match input.peek(Token![*]) {
+                true => {
+                    let _: Token![*] = input.parse()?;
+                    segments.push(Segment::Wildcard);
+                }
+                false => {
+                    let ident = syn::Ident::parse_any(input)?;
+                    segments.push(Segment::Key(ident.unraw().to_string()));
+                }
+            }
+
+            match input.peek(Token![.]) {
+                true => {
+                    let _: Token![.] = input.parse()?;
+                }
+                false => break,
+            }
+        }
+
+        Ok(Self { segments, span })
+    }
+}
+
+impl Parse for TaggedArray {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (path, tag) = parse_by(input)?;
+
+        Ok(Self { path, tag })
+    }
+}
+
+impl Parse for IndexedArray {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let (path, key) = parse_by(input)?;
+
+        Ok(Self { path, key })
+    }
+}
+
+/// A key path and a key inside the tables at that path, e.g. `listeners by kind`.
+fn parse_by(input: syn::parse::ParseStream) -> syn::Result<(KeyPattern, String)> {
+    let path: KeyPattern = input.parse()?;
+
+    let by = syn::Ident::parse_any(input)?;
+    if by != "by" {
+        return Err(syn::Error::new(by.span(), "expected `by`"));
+    }
+
+    let key = match input.peek(LitStr) {
+        true => input.parse::<LitStr>()?.value(),
+        false => syn::Ident::parse_any(input)?.unraw().to_string(),
+    };
+
+    Ok((path, key))
+}
+
+impl Parse for TypeOverride {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let ty = ValueType::from_type(&input.parse::<syn::Type>()?)?;
+
+        Ok(Self { path, ty })
+    }
+}
+
+impl Parse for EmbeddedFile {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+
+        let kind = match input.peek(Token![=]) {
+            true => {
+                let _: Token![=] = input.parse()?;
+                let kind: syn::Ident = input.parse()?;
+
+                match kind.to_string().as_str() {
+                    "bytes" => EmbedKind::Bytes,
+                    "str" => EmbedKind::Str,
+                    _ => return Err(syn::Error::new(kind.span(), "expected `bytes` or `str`")),
+                }
+            }
+            false => EmbedKind::Bytes,
+        };
+
+        Ok(Self(TypeOverride {
+            path,
+            ty: ValueType::Embed(kind),
+        }))
+    }
+}
+
+impl Parse for ResolvedPath {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+
+        let base = match input.peek(Token![=]) {
+            true => {
+                let _: Token![=] = input.parse()?;
+                let base: syn::Ident = input.parse()?;
+
+                match base.to_string().as_str() {
+                    "file" => PathBase::File,
+                    "manifest" => PathBase::Manifest,
+                    _ => {
+                        return Err(syn::Error::new(
+                            base.span(),
+                            "expected `file` or `manifest`",
+                        ))
+                    }
+                }
+            }
+            false => PathBase::File,
+        };
+
+        Ok(Self(TypeOverride {
+            path,
+            ty: ValueType::Path(base),
+        }))
+    }
+}
+
+impl Parse for ValueSet {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+
+        if !input.peek(Token![=]) {
+            return Ok(Self { path, values: None });
+        }
+        let _: Token![=] = input.parse()?;
+
+        let content;
+        syn::bracketed!(content in input);
+        let values =
+            Punctuated::<String, Token![,]>::parse_terminated_with(&content, |value| match value
+                .peek(LitStr)
+            {
+                true => Ok(value.parse::<LitStr>()?.value()),
+                false => Ok(syn::Ident::parse_any(value)?.unraw().to_string()),
+            })?;
+
+        if values.is_empty() {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("allowed values for {} must not be empty", path),
+            ));
+        }
+
+        Ok(Self {
+            path,
+            values: Some(values.into_iter().collect()),
+        })
+    }
+}
+
+impl Parse for RenamedKey {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+        let _: Token![=] = input.parse()?;
+
+        let name = match input.peek(LitStr) {
+            true => input.parse::<LitStr>()?.value(),
+            false => syn::Ident::parse_any(input)?.unraw().to_string(),
+        };
+
+        Ok(Self { path, name })
+    }
+}
+
+impl Parse for NamingItem {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let name: syn::Ident = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let value = syn::Ident::parse_any(input)?;
+
+        let item = match (name.to_string().as_str(), value.to_string().as_str()) {
+            ("fields", "snake_case") => NamingItem::Fields(FieldNaming::SnakeCase),
+            ("fields", "original") => NamingItem::Fields(FieldNaming::Original),
+            ("fields", _) => {
+                return Err(syn::Error::new(
+                    value.span(),
+                    "expected `snake_case` or `original`",
+                ))
+            }
+            ("types", "pascal_case") => NamingItem::Types(TypeNaming::PascalCase),
+            ("types", "upper_camel_case") => NamingItem::Types(TypeNaming::UpperCamelCase),
+            ("types", _) => {
+                return Err(syn::Error::new(
+                    value.span(),
+                    "expected `pascal_case` or `upper_camel_case`",
+                ))
+            }
+            ("type_prefix", _) => NamingItem::TypePrefix(value.unraw().to_string()),
+            ("type_suffix", _) => NamingItem::TypeSuffix(value.unraw().to_string()),
+            _ => {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "expected `fields`, `types`, `type_prefix` or `type_suffix`",
+                ))
+            }
+        };
+
+        Ok(item)
+    }
+}
+
+impl Parse for MappedTable {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: KeyPattern = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let target: MapTarget = input.parse()?;
+
+        Ok(Self { path, target })
+    }
+}
+
+impl Parse for MapTarget {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let path: syn::Path = input.parse()?;
+
+        let names = |content: syn::parse::ParseStream| {
+            Punctuated::<syn::Ident, Token![,]>::parse_terminated_with(
+                content,
+                syn::Ident::parse_any,
+            )
+            .map(|names| {
+                names
+                    .into_iter()
+                    .map(|n| n.unraw().to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // Type { a, b }
+        if input.peek(syn::token::Brace) {
+            let content;
+            syn::braced!(content in input);
+
+            return Ok(Self {
+                ty: path.to_token_stream().to_string(),
+                init: MapInit::Fields(names(&content)?),
+            });
+        }
+
+        // Type::new(a, b)
+        if input.peek(syn::token::Paren) {
+            let content;
+            syn::parenthesized!(content in input);
+            let args = names(&content)?;
+
+            let mut ty = path.clone();
+            ty.segments.pop();
+            ty.segments.pop_punct();
+            if ty.segments.is_empty() {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "expected a constructor path, e.g. `Type::new(a, b)`",
+                ));
+            }
+
+            return Ok(Self {
+                ty: ty.to_token_stream().to_string(),
+                init: MapInit::Constructor {
+                    func: path.to_token_stream().to_string(),
+                    args,
+                },
+            });
+        }
+
+        Ok(Self {
+            ty: path.to_token_stream().to_string(),
+            init: MapInit::Literal,
+        })
+    }
+}
+
+/// Options that take a comma separated list of items, e.g. `#[tagged(a by b, c by d)]`.
+fn parse_list<T: Parse>(attr: &syn::Attribute) -> Result<Vec<T>, syn::Error> {
+    let items = attr.parse_args_with(Punctuated::<T, Token![,]>::parse_terminated)?;
+
+    Ok(items.into_iter().collect())
+}
+
+/// Options that do not take any arguments, e.g. `#[promote_numbers]`.
+fn parse_flag(attr: &syn::Attribute, attr_path: &str) -> Result<bool, syn::Error> {
+    match &attr.meta {
+        syn::Meta::Path(_) => Ok(true),
+        other => Err(syn::Error::new(
+            other.span(),
+            format!("#[{}] does not take any arguments", attr_path),
+        )),
+    }
+}
+
+/// Match a key against a pattern where `*` matches any characters, e.g. `*_timeout`.
+fn glob_matches(glob: &str, key: &str) -> bool {
+    match glob.split_once('*') {
+        None => glob == key,
+        Some((prefix, rest)) => {
+            key.starts_with(prefix)
+                && (prefix.len()..=key.len())
+                    .filter(|i| key.is_char_boundary(*i))
+                    .any(|i| glob_matches(rest, &key[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flag_options() {
+        let attrs: Vec<syn::Attribute> = vec![
+            syn::parse_quote! {#[derive(PartialEq)]},
+            syn::parse_quote! {#[promote_numbers]},
+            syn::parse_quote! {#[mixed_arrays]},
+            syn::parse_quote! {#[fixed_arrays(matrix, "lut.*")]},
+            syn::parse_quote! {#[suffix_collisions]},
+            syn::parse_quote! {#[shared_types]},
+        ];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert!(options.promote_numbers);
+        assert!(options.mixed_arrays);
+        assert!(options.suffix_collisions);
+        assert!(options.shared_types);
+        assert!(!options.fixed_arrays);
+        assert!(options.is_fixed_array(&["matrix".to_string()]));
+        assert!(options.is_fixed_array(&["lut".to_string(), "sin".to_string()]));
+        assert!(!options.is_fixed_array(&["lut".to_string()]));
+        assert!(!MacroOptions::is_option_attr(&attrs[0]));
+        assert!(MacroOptions::is_option_attr(&attrs[1]));
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[promote_numbers(yes)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_namespace_option() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {#[namespace(cfg_types)]}];
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.namespace.unwrap().to_string(), "cfg_types");
+        assert!(MacroOptions::default().namespace.is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[namespace(a, b)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_key_pattern() {
+        let path = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        let pattern: KeyPattern = syn::parse_quote! {servers.*.port};
+        assert!(pattern.matches(&path(&["servers", "alpha", "port"])));
+        assert!(!pattern.matches(&path(&["servers", "alpha"])));
+        assert!(!pattern.matches(&path(&["clients", "alpha", "port"])));
+        assert_eq!(pattern.to_string(), "servers.*.port");
+
+        let pattern: KeyPattern = syn::parse_quote! {"site.'google.com'"};
+        assert!(pattern.matches(&path(&["site", "google.com"])));
+
+        let pattern: KeyPattern = syn::parse_quote! {r#type};
+        assert!(pattern.matches(&path(&["type"])));
+
+        let pattern: KeyPattern = syn::parse_quote! {"**.*_timeout"};
+        assert!(pattern.matches(&path(&["read_timeout"])));
+        assert!(pattern.matches(&path(&["server", "http", "idle_timeout"])));
+        assert!(!pattern.matches(&path(&["server", "timeout"])));
+        assert!(!pattern.matches(&path(&["read_timeout", "ms"])));
+        assert_eq!(pattern.to_string(), "**.*_timeout");
+
+        let pattern: KeyPattern = syn::parse_quote! {"cache.*size*"};
+        assert!(pattern.matches(&path(&["cache", "size"])));
+        assert!(pattern.matches(&path(&["cache", "max_size_total"])));
+        assert!(!pattern.matches(&path(&["cache", "max_len"])));
+
+        assert!(syn::parse_str::<KeyPattern>("\"a..b\"").is_err());
+        assert!(syn::parse_str::<KeyPattern>("\"a.'b\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_tagged() {
+        let attrs: Vec<syn::Attribute> = vec![
+            syn::parse_quote! {#[tagged(listeners by kind, "a.b" by r#type)]},
+            syn::parse_quote! {#[tagged(routes by "type")]},
+        ];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.tagged.len(), 3);
+        assert_eq!(options.tagged[1].tag, "type");
+        assert_eq!(options.tagged[2].tag, "type");
+        assert!(options
+            .tagged_array(&["listeners".to_string()])
+            .is_some_and(|t| t.tag == "kind"));
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[tagged(listeners with kind)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let attrs: Vec<syn::Attribute> =
+            vec![syn::parse_quote! {#[index(products by name, "servers.*.routes" by "path")]}];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.indexed.len(), 2);
+        assert_eq!(options.indexed[1].key, "path");
+        assert!(matches!(
+            options.indexed_array(&["products".to_string()]),
+            Some(i) if i.key == "name"
+        ));
+        assert!(options.indexed_array(&["routes".to_string()]).is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[index(products)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_table_maps() {
+        let attrs: Vec<syn::Attribute> = vec![
+            syn::parse_quote! {#[table_map(servers, "limits.*")]},
+            syn::parse_quote! {#[no_table_map(settings, limits.fixed)]},
+        ];
+        let path = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.table_map(&path(&["servers"])), Some(true));
+        assert_eq!(options.table_map(&path(&["settings"])), Some(false));
+        assert_eq!(options.table_map(&path(&["limits", "fixed"])), Some(false));
+        assert_eq!(options.table_map(&path(&["limits", "soft"])), Some(true));
+        assert_eq!(options.table_map(&path(&["other"])), None);
+    }
+
+    #[test]
+    fn test_parse_type_overrides() {
+        let attrs: Vec<syn::Attribute> = vec![
+            syn::parse_quote! {#[ty("server.port" = u16, limits.ratio = f32)]},
+            syn::parse_quote! {#[ty("pool.size" = core::num::NonZeroUsize)]},
+        ];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.value_types.len(), 3);
+        assert!(options
+            .value_type(&["limits".to_string(), "ratio".to_string()])
+            .is_some_and(|t| t.ty == ValueType::F32));
+        assert!(options.value_type(&["server".to_string()]).is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[ty("server.port" = String)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_embedded_files_and_paths() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {
+            #[embed(logo, "templates.*" = str, certs = bytes)]
+        }];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert!(options
+            .value_type(&["logo".to_string()])
+            .is_some_and(|t| t.ty == ValueType::Embed(EmbedKind::Bytes)));
+        assert!(options
+            .value_type(&["templates".to_string(), "index".to_string()])
+            .is_some_and(|t| t.ty == ValueType::Embed(EmbedKind::Str)));
+        assert!(options.value_type(&["templates".to_string()]).is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[embed(logo = png)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {
+            #[path(assets, "plugins.*" = manifest)]
+        }];
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert!(options
+            .value_type(&["assets".to_string()])
+            .is_some_and(|t| t.ty == ValueType::Path(PathBase::File)));
+        assert!(options
+            .value_type(&["plugins".to_string(), "a".to_string()])
+            .is_some_and(|t| t.ty == ValueType::Path(PathBase::Manifest)));
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[path(assets = cwd)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_enum_values() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {
+            #[enum_values("log_level" = [trace, debug, "warn-only"], routes.method)]
+        }];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(
+            options.enum_values[0].values.as_deref(),
+            Some(
+                &[
+                    "trace".to_string(),
+                    "debug".to_string(),
+                    "warn-only".to_string()
+                ][..]
+            )
+        );
+        assert!(options
+            .enum_values(&["routes".to_string(), "method".to_string()])
+            .is_some_and(|e| e.values.is_none()));
+
+        let flags: Vec<syn::Attribute> = vec![syn::parse_quote! {#[flags(capabilities)]}];
+        let options = MacroOptions::from_attrs(&flags).expect("options must parse");
+        assert!(options.flags(&["capabilities".to_string()]).is_some());
+        assert!(options.enum_values(&["capabilities".to_string()]).is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[enum_values(level = [])]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_renamed_keys() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {
+            #[rename("'weird key'" = weird_key, "servers.*.'名前'" = name, r#type = "kind")]
+        }];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.renamed.len(), 3);
+        assert!(options
+            .renamed_key(&["weird key".to_string()])
+            .is_some_and(|r| r.name == "weird_key"));
+        assert!(options
+            .renamed_key(&["servers".to_string(), "a".to_string(), "名前".to_string()])
+            .is_some_and(|r| r.name == "name"));
+        assert!(options
+            .renamed_key(&["type".to_string()])
+            .is_some_and(|r| r.name == "kind"));
+        assert!(options.renamed_key(&["servers".to_string()]).is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[rename("weird key")]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_naming() {
+        let attrs: Vec<syn::Attribute> = vec![
+            syn::parse_quote! {#[naming(fields = original, types = upper_camel_case)]},
+            syn::parse_quote! {#[naming(type_prefix = Config, type_suffix = Toml)]},
+        ];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(
+            options.naming,
+            Naming {
+                fields: FieldNaming::Original,
+                types: TypeNaming::UpperCamelCase,
+                type_prefix: "Config".to_string(),
+                type_suffix: "Toml".to_string(),
+            }
+        );
+        assert_eq!(MacroOptions::default().naming, Naming::default());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[naming(fields = camel)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[naming(prefix = Config)]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_parse_mapped_tables() {
+        let attrs: Vec<syn::Attribute> = vec![syn::parse_quote! {
+            #[map(
+                "servers.*" = crate::ServerConfig,
+                limits = Limits { max, r#min },
+                pool = pool::Pool::new(size, timeout),
+            )]
+        }];
+
+        let options = MacroOptions::from_attrs(&attrs).expect("options must parse");
+        assert_eq!(options.mapped.len(), 3);
+        assert_eq!(options.mapped[0].target.init(), &MapInit::Literal);
+        assert_eq!(options.mapped[0].target.to_string(), "crate::ServerConfig");
+        assert_eq!(
+            options.mapped[1].target.init(),
+            &MapInit::Fields(vec!["max".to_string(), "min".to_string()])
+        );
+        assert_eq!(options.mapped[2].target.to_string(), "pool::Pool");
+
+        let keys = ["max".to_string(), "avg".to_string()];
+        let err = options.mapped[1]
+            .target
+            .check_keys(&keys)
+            .expect_err("keys must not match");
+        assert_eq!(
+            err,
+            "keys do not match Limits: missing keys `min`, extra keys `avg`"
+        );
+
+        // bare struct literals are checked against the fields that the type lists
+        let runtime_path: syn::Path = syn::parse_quote!(toml_const);
+        let path = ["servers".to_string(), "alpha".to_string()];
+        let check = options.mapped[0]
+            .target
+            .field_check(&keys, &path, &runtime_path)
+            .expect("struct literals are checked");
+        assert!(check
+            .to_string()
+            .contains("\"servers.alpha: keys do not match crate::ServerConfig\""));
+        assert!(check.to_string().contains("__private :: Fields"));
+        assert!(options.mapped[1]
+            .target
+            .field_check(&keys, &path, &runtime_path)
+            .is_none());
+
+        let invalid: Vec<syn::Attribute> = vec![syn::parse_quote! {#[map(pool = new(size))]}];
+        assert!(MacroOptions::from_attrs(&invalid).is_err());
+    }
+}
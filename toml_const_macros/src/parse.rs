@@ -1,15 +1,21 @@
 //! Custom input syntax for proc-macro inputs
 
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use proc_macro2 as pm2;
-use proc_macro2::{Delimiter, Group};
+use proc_macro2::{Delimiter, Group, Span};
 use quote::{quote, ToTokens, TokenStreamExt};
+use syn::ext::IdentExt;
 use syn::spanned::Spanned;
 use syn::{braced, parse::Parse, punctuated::Punctuated, LitStr};
 use syn::{Ident, Token};
 
+use crate::instantiate::ConstIdentDef;
+use crate::options::{MacroOptions, Naming, TypeOverride};
+use crate::types::{PathBase, ValueType};
+
 // attributes to forward
 const INSTANTIATION_ATTR_PATH: &str = "instance";
 const DEFINITION_ATTR_PATH: &str = "define";
@@ -23,8 +29,8 @@ pub struct MacroInput {
     pub attrs: Vec<syn::Attribute>,
 
     // pub destructure_datetime: bool,
-    /// Whether the static variable is public
-    pub is_pub: bool,
+    /// Visibility of the static variable
+    pub vis: syn::Visibility,
 
     /// `false` if static, `true` if const
     pub static_const: bool,
@@ -67,16 +73,7 @@ impl Parse for MacroInput {
         // parse docstring and datetime attr
         let attrs = input.call(syn::Attribute::parse_outer).unwrap_or_default();
 
-        let is_pub: bool = {
-            let lookahead = input.lookahead1();
-            match lookahead.peek(syn::Token![pub]) {
-                true => {
-                    let _: syn::Token![pub] = input.parse()?;
-                    true
-                }
-                false => false,
-            }
-        };
+        let vis: syn::Visibility = input.parse()?;
 
         let static_const = {
             let lookahead = input.lookahead1();
@@ -140,7 +137,7 @@ impl Parse for MacroInput {
             )),
             false => Ok(Self {
                 attrs,
-                is_pub,
+                vis,
                 static_const,
                 item_ident,
                 is_final,
@@ -157,9 +154,7 @@ impl ToTokens for MacroInput {
             attr.to_tokens(tokens);
         }
 
-        if self.is_pub {
-            quote! {pub}.to_tokens(tokens);
-        }
+        self.vis.to_tokens(tokens);
 
         match self.static_const {
             true => quote! {const}.to_tokens(tokens),
@@ -267,17 +262,17 @@ impl MacroInput {
     ///
     /// The input base path must be absolute.
     pub fn to_abs_path(&self, base_path: &Path) -> Self {
-        let mut abs_base_path = base_path.to_path_buf();
-
-        abs_base_path.push(PathBuf::from(self.path.value()));
+        // missing files are reported when they are read
+        let abs_base_path =
+            abs_path(base_path, &self.path.value()).unwrap_or_else(|(joined, _)| joined);
         let abs_base_path = LitStr::new(pathbuf_to_str(&abs_base_path), self.path.span());
 
         let sub_paths = self.sub_paths.clone();
         let sub_paths = sub_paths.map(|sp| {
             sp.into_iter()
                 .map(|p| {
-                    let mut abs_sub_path = base_path.to_path_buf();
-                    abs_sub_path.push(PathBuf::from(p.path.value()));
+                    let abs_sub_path =
+                        abs_path(base_path, &p.path.value()).unwrap_or_else(|(joined, _)| joined);
                     let new_path = LitStr::new(pathbuf_to_str(&abs_sub_path), p.path.span());
 
                     UsePath {
@@ -296,8 +291,15 @@ impl MacroInput {
     }
 
     /// With the the data in `self`, read in the template file and apply any substitutions
-    pub fn generate_toml_table(&self) -> Result<toml::Table, pm2::TokenStream> {
-        let template_toml = read_litstr_to_toml(&self.path)?.ok_or(
+    ///
+    /// Paths to embedded files are resolved relative to the file they are found in.
+    /// Path values are resolved relative to that file, or to `manifest_dir`.
+    pub fn generate_toml_table(
+        &self,
+        options: &MacroOptions,
+        manifest_dir: &Path,
+    ) -> Result<toml::Table, pm2::TokenStream> {
+        let template_toml = read_litstr_to_toml(&self.path, options, manifest_dir)?.ok_or(
             syn::Error::new(
                 self.path.span(),
                 format!("unable to read template file: {}", self.path.value()),
@@ -310,7 +312,7 @@ impl MacroInput {
                 let mut res_sub = None;
 
                 for sub_path in paths.iter() {
-                    let sub_toml = read_litstr_to_toml(&sub_path.path)?;
+                    let sub_toml = read_litstr_to_toml(&sub_path.path, options, manifest_dir)?;
                     let sub_toml = match sub_toml {
                         Some(st) => st,
                         None => continue,
@@ -379,7 +381,9 @@ impl MacroInput {
     }
 
     fn define_attr(attr: &syn::Attribute) -> Result<Option<syn::Attribute>, syn::Error> {
-        if attr.path().is_ident("derive") {
+        if MacroOptions::is_option_attr(attr) {
+            Ok(None)
+        } else if attr.path().is_ident("derive") {
             Ok(Some(attr.clone()))
         } else if attr.path().is_ident(DEFINITION_ATTR_PATH) {
             Self::strip_attr_path_and_transform(attr, DEFINITION_ATTR_PATH)
@@ -394,7 +398,9 @@ impl MacroInput {
     ///
     /// Transforms the contents of the attribute, if applicable.
     fn instance_attr(attr: &syn::Attribute) -> Result<Option<syn::Attribute>, syn::Error> {
-        if attr.path().is_ident("doc") {
+        if MacroOptions::is_option_attr(attr) {
+            Ok(None)
+        } else if attr.path().is_ident("doc") {
             Ok(Some(attr.clone()))
         } else if attr.path().is_ident(INSTANTIATION_ATTR_PATH) {
             Self::strip_attr_path_and_transform(attr, INSTANTIATION_ATTR_PATH)
@@ -419,6 +425,11 @@ impl MacroInput {
             .collect::<Result<Vec<_>, _>>()
     }
 
+    /// Returns all options set on this input. Options are not forwarded.
+    pub fn options(&self) -> Result<MacroOptions, syn::Error> {
+        MacroOptions::from_attrs(&self.attrs)
+    }
+
     /// Returns all attributes that should be forwarded to instantiation
     pub fn instantiation_attrs(&self) -> Result<Vec<syn::Attribute>, syn::Error> {
         self.attrs
@@ -501,8 +512,21 @@ fn pathbuf_to_str(input: &Path) -> &str {
     input.to_str().expect("failed to convert path to str")
 }
 
+/// Join a path to an absolute base directory and canonicalize it.
+///
+/// Paths that cannot be canonicalized, e.g. missing files, are returned joined with the error.
+fn abs_path(base_dir: &Path, path: &str) -> Result<PathBuf, (PathBuf, std::io::Error)> {
+    let joined = base_dir.join(path);
+
+    joined.canonicalize().map_err(|e| (joined, e))
+}
+
 /// Read in a litstr path to a toml file, return an error tokenstream if it fails.
-fn read_litstr_to_toml(litstr: &LitStr) -> Result<Option<toml::Table>, pm2::TokenStream> {
+fn read_litstr_to_toml(
+    litstr: &LitStr,
+    options: &MacroOptions,
+    manifest_dir: &Path,
+) -> Result<Option<toml::Table>, pm2::TokenStream> {
     let path = PathBuf::from(litstr.value());
 
     // we allow paths that do not resolve to a file
@@ -510,7 +534,7 @@ fn read_litstr_to_toml(litstr: &LitStr) -> Result<Option<toml::Table>, pm2::Toke
         return Ok(None);
     }
 
-    let file = match fs::read_to_string(path) {
+    let file = match fs::read_to_string(&path) {
         Ok(tf) => tf,
         Err(e) => {
             return Err(syn::Error::new(litstr.span(), e.to_string())
@@ -528,9 +552,210 @@ fn read_litstr_to_toml(litstr: &LitStr) -> Result<Option<toml::Table>, pm2::Toke
         }
     };
 
+    let mut template_toml =
+        rename_table(template_toml, options, &mut Vec::new()).map_err(|e| e.to_compile_error())?;
+
+    let resolver = PathResolver {
+        file_dir: path.parent().unwrap_or(Path::new("")),
+        manifest_dir,
+        options,
+    };
+    resolver
+        .resolve_table(&mut template_toml, &mut Vec::new())
+        .map_err(|e| e.to_compile_error())?;
+
     Ok(Some(template_toml))
 }
 
+/// Rename keys in a table read from a TOML file, and all tables inside it.
+///
+/// Keys are renamed before any other options are applied, so other options match the new keys.
+fn rename_table(
+    table: toml::Table,
+    options: &MacroOptions,
+    path: &mut Vec<String>,
+) -> Result<toml::Table, syn::Error> {
+    let mut renamed = toml::Table::new();
+    let mut idents = HashSet::new();
+
+    for (key, mut value) in table {
+        path.push(key);
+        rename_value(&mut value, options, path)?;
+
+        let rename = options.renamed_key(path);
+        let key = path.pop().expect("key was pushed");
+        let mut new_key = rename.map(|r| r.name.clone()).unwrap_or(key);
+
+        if options.suffix_collisions {
+            new_key = suffix_key(new_key, &value, &mut idents, &options.naming);
+        }
+
+        if renamed.contains_key(&new_key) {
+            let span = rename.map(|r| r.path.span()).unwrap_or(Span::call_site());
+            path.push(new_key);
+            let msg = format!("cannot rename to {}, key already exists", path.join("."));
+            return Err(syn::Error::new(span, msg));
+        }
+        renamed.insert(new_key, value);
+    }
+
+    Ok(renamed)
+}
+
+/// Append the first suffix, e.g. `_2`, that makes the identifiers of a key distinct from earlier keys.
+///
+/// Tables and arrays may also generate types, so their type identifiers must be distinct too.
+fn suffix_key(
+    key: String,
+    value: &toml::Value,
+    idents: &mut HashSet<String>,
+    naming: &Naming,
+) -> String {
+    let key_idents = |key: &str| {
+        let mut key_idents = vec![key.to_module_ident(naming).unraw().to_string()];
+        if matches!(value, toml::Value::Table(_) | toml::Value::Array(_)) {
+            key_idents.push(key.to_type_ident(naming).to_string());
+        }
+        key_idents
+    };
+
+    let suffixed = (1..)
+        .map(|n| match n {
+            1 => key.clone(),
+            n => format!("{}_{}", key, n),
+        })
+        .find(|k| key_idents(k).iter().all(|i| !idents.contains(i)))
+        .expect("suffixes are unbounded");
+    idents.extend(key_idents(&suffixed));
+
+    suffixed
+}
+
+fn rename_value(
+    value: &mut toml::Value,
+    options: &MacroOptions,
+    path: &mut Vec<String>,
+) -> Result<(), syn::Error> {
+    match value {
+        // array elements share the key path of the array
+        toml::Value::Array(values) => values
+            .iter_mut()
+            .try_for_each(|v| rename_value(v, options, path)),
+        toml::Value::Table(table) => {
+            *table = rename_table(std::mem::take(table), options, path)?;
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolves paths to embedded files and path values in a table read from a TOML file.
+struct PathResolver<'a> {
+    /// Directory of the TOML file
+    file_dir: &'a Path,
+    /// Directory that the macro resolves the TOML file from, i.e. the manifest or workspace
+    manifest_dir: &'a Path,
+    options: &'a MacroOptions,
+}
+
+impl PathResolver<'_> {
+    fn resolve_table(
+        &self,
+        table: &mut toml::Table,
+        path: &mut Vec<String>,
+    ) -> Result<(), syn::Error> {
+        for (key, value) in table.iter_mut() {
+            path.push(key.clone());
+            self.resolve_value(value, path)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    fn resolve_value(
+        &self,
+        value: &mut toml::Value,
+        path: &mut Vec<String>,
+    ) -> Result<(), syn::Error> {
+        match self.options.value_type(path) {
+            // embedded files are declared as `"path"` or `{ file = "path" }`, and checked when converted
+            Some(TypeOverride {
+                ty: ValueType::Embed(_),
+                ..
+            }) => {
+                let file = match value {
+                    toml::Value::String(file) => Some(file.clone()),
+                    toml::Value::Table(table) if table.len() == 1 => match table.get("file") {
+                        Some(toml::Value::String(file)) => Some(file.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                if let Some(file) = file {
+                    let abs_path =
+                        abs_path(self.file_dir, &file).unwrap_or_else(|(joined, _)| joined);
+                    *value = toml::Value::String(pathbuf_to_str(&abs_path).to_string());
+                    return Ok(());
+                }
+            }
+            Some(TypeOverride {
+                ty: ValueType::Path(base),
+                path: pattern,
+            }) => {
+                if let toml::Value::String(value_path) = value {
+                    let resolved = self.resolve_path(value_path, *base).map_err(|msg| {
+                        syn::Error::new(pattern.span(), format!("{}: {}", path.join("."), msg))
+                    })?;
+                    *value = toml::Value::String(resolved);
+                    return Ok(());
+                }
+            }
+            _ => (),
+        }
+
+        match value {
+            // array elements share the key path of the array
+            toml::Value::Array(values) => values
+                .iter_mut()
+                .try_for_each(|v| self.resolve_value(v, path)),
+            toml::Value::Table(table) => self.resolve_table(table, path),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a path exists, and return its canonical form.
+    ///
+    /// Paths relative to the manifest are returned relative to the manifest if they are inside it,
+    /// and as canonical absolute paths otherwise.
+    fn resolve_path(&self, value_path: &str, base: PathBase) -> Result<String, String> {
+        let base_dir = match base {
+            PathBase::File => self.file_dir,
+            PathBase::Manifest => self.manifest_dir,
+        };
+        let canonical = abs_path(base_dir, value_path).map_err(|(joined, e)| {
+            format!(
+                "path \"{}\" does not exist, resolved to {}: {}",
+                value_path,
+                joined.display(),
+                e
+            )
+        })?;
+
+        let resolved = match base {
+            PathBase::File => canonical,
+            PathBase::Manifest => match canonical.strip_prefix(self.manifest_dir) {
+                Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => canonical,
+            },
+        };
+
+        Ok(pathbuf_to_str(&resolved).to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -553,6 +778,62 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_resolve_manifest_path() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let options = MacroOptions::default();
+        let resolver = PathResolver {
+            file_dir: &manifest_dir,
+            manifest_dir: &manifest_dir,
+            options: &options,
+        };
+
+        assert_eq!(
+            resolver.resolve_path("./src/../src", PathBase::Manifest),
+            Ok("src".to_string())
+        );
+        assert_eq!(
+            resolver.resolve_path(".", PathBase::Manifest),
+            Ok(".".to_string())
+        );
+
+        // paths outside the manifest are canonical
+        let outside = resolver
+            .resolve_path("../toml_const/../Cargo.toml", PathBase::Manifest)
+            .expect("path must exist");
+        assert_eq!(
+            PathBuf::from(outside),
+            manifest_dir.join("../Cargo.toml").canonicalize().unwrap()
+        );
+        assert!(resolver
+            .resolve_path("missing.toml", PathBase::Manifest)
+            .is_err());
+    }
+
+    #[test]
+    fn test_to_abs_path() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .canonicalize()
+            .unwrap();
+        let input: MacroInput = syn::parse_quote! {
+            const X: "./src/../Cargo.toml";
+        };
+
+        // paths are canonical, like resolved path values
+        assert_eq!(
+            PathBuf::from(input.to_abs_path(&manifest_dir).path.value()),
+            manifest_dir.join("Cargo.toml")
+        );
+
+        let missing: MacroInput = syn::parse_quote! {
+            const X: "missing.toml";
+        };
+        assert_eq!(
+            PathBuf::from(missing.to_abs_path(&manifest_dir).path.value()),
+            manifest_dir.join("missing.toml")
+        );
+    }
+
     test_parse!(MacroInput: test_parse_template_new {
         const X: "some_file_path.toml";
     });
@@ -657,6 +938,7 @@ mod tests {
         }
         test_forward! {MacroInput::define_attr(#[instance(some_instance_attr)]) = Ok(None), "instance not forwarded"}
         test_forward! {MacroInput::define_attr(#[rustfmt::skip]) = Ok(Some(_)), "non matching attr paths are all forwarded"};
+        test_forward! {MacroInput::define_attr(#[promote_numbers]) = Ok(None), "options are not forwarded"};
 
         // instances
         test_forward! {MacroInput::instance_attr(#[derive(Clone, Debug)]) = Ok(None), "derives are not forwarded"}
@@ -673,6 +955,7 @@ mod tests {
         }
         test_forward! {MacroInput::instance_attr(#[define(some_define_attr)]) = Ok(None), "defines are not forwarded"};
         test_forward! {MacroInput::instance_attr(#[rustfmt::skip]) = Ok(Some(_)), "non matching attr paths are all forwarded"};
+        test_forward! {MacroInput::instance_attr(#[promote_numbers]) = Ok(None), "options are not forwarded"};
     }
 
     /// Test attribute detection and forwarding
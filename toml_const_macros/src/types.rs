@@ -0,0 +1,1049 @@
+//! Value type overrides.
+//!
+//! Values are generated as `i64`, `f64`, etc. by default.
+//! A value type override generates a value as another type, after checking that the value fits.
+//!
+//! Durations and byte sizes are parsed from unit-suffixed strings, e.g. `"1h30m"` or `"512KiB"`.
+//! Addresses, versions and colors are parsed from strings, e.g. `"127.0.0.1:8080"`, `"1.2.3-beta"` or `"#ff8800"`.
+//! Embedded files are paths whose contents are included with `include_bytes!` or `include_str!`.
+//! Paths to files and directories are resolved and checked before values are converted.
+
+use proc_macro2::{self as pm2, Span};
+use quote::quote;
+use syn::spanned::Spanned;
+
+/// Primitive integer types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntType {
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+}
+
+/// Types that a value can be generated as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    Int(IntType),
+    /// `core::num::NonZero*` integers
+    NonZero(IntType),
+    F32,
+    F64,
+    /// `core::time::Duration`, parsed from strings like `"1h30m"`
+    Duration,
+    /// Byte counts as `u64`, parsed from strings like `"512KiB"`
+    ByteSize,
+    /// `core::net` addresses
+    IpAddr,
+    Ipv4Addr,
+    Ipv6Addr,
+    SocketAddr,
+    /// Semantic versions, e.g. `"1.2.3-beta"`
+    Version,
+    /// Colors, e.g. `"#ff8800"`
+    Rgb,
+    /// Colors with an alpha channel, e.g. `"#ff880080"`
+    Rgba,
+    /// Contents of a file, from an absolute path
+    Embed(EmbedKind),
+    /// A path that exists, resolved relative to the TOML file or the manifest
+    Path(PathBase),
+    /// `&'static CStr`, from a `c"..."` literal
+    CStr,
+    /// `&'static [u8]`, from a `b"..."` literal
+    ByteStr,
+}
+
+/// How an embedded file is included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmbedKind {
+    /// `&'static [u8]`, with `include_bytes!`
+    Bytes,
+    /// `&'static str`, with `include_str!`
+    Str,
+}
+
+/// What a path is relative to, and how it is generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PathBase {
+    /// Relative to the TOML file, generated as an absolute path
+    File,
+    /// Relative to the manifest or workspace, generated as a relative path
+    Manifest,
+}
+
+/// A parsed semantic version.
+#[derive(Debug, PartialEq)]
+struct Version<'a> {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: &'a str,
+    build: &'a str,
+}
+
+/// Duration units and their length in nanoseconds.
+const DURATION_UNITS: &[(&str, u128)] = &[
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+    ("m", 60 * 1_000_000_000),
+    ("h", 60 * 60 * 1_000_000_000),
+    ("d", 24 * 60 * 60 * 1_000_000_000),
+];
+
+/// Byte size units, matched case-insensitively.
+const BYTE_UNITS: &[(&str, u128)] = &[
+    ("b", 1),
+    ("k", 1_000),
+    ("kb", 1_000),
+    ("kib", 1 << 10),
+    ("m", 1_000_000),
+    ("mb", 1_000_000),
+    ("mib", 1 << 20),
+    ("g", 1_000_000_000),
+    ("gb", 1_000_000_000),
+    ("gib", 1 << 30),
+    ("t", 1_000_000_000_000),
+    ("tb", 1_000_000_000_000),
+    ("tib", 1 << 40),
+    ("p", 1_000_000_000_000_000),
+    ("pb", 1_000_000_000_000_000),
+    ("pib", 1 << 50),
+];
+
+impl IntType {
+    pub const ALL: &'static [IntType] = &[
+        IntType::I8,
+        IntType::I16,
+        IntType::I32,
+        IntType::I64,
+        IntType::I128,
+        IntType::Isize,
+        IntType::U8,
+        IntType::U16,
+        IntType::U32,
+        IntType::U64,
+        IntType::U128,
+        IntType::Usize,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            IntType::I8 => "i8",
+            IntType::I16 => "i16",
+            IntType::I32 => "i32",
+            IntType::I64 => "i64",
+            IntType::I128 => "i128",
+            IntType::Isize => "isize",
+            IntType::U8 => "u8",
+            IntType::U16 => "u16",
+            IntType::U32 => "u32",
+            IntType::U64 => "u64",
+            IntType::U128 => "u128",
+            IntType::Usize => "usize",
+        }
+    }
+
+    /// Name of the matching `NonZero*` type, e.g. `NonZeroU16`.
+    fn non_zero_name(&self) -> String {
+        let name = self.name();
+        format!("NonZero{}{}", name[..1].to_uppercase(), &name[1..])
+    }
+
+    /// Inclusive range of values that fit in this type.
+    ///
+    /// `isize` and `usize` allow the 64 bit range here; values that do not fit in 16 bits are
+    /// checked against the target pointer width when compiled, see [`IntType::literal`].
+    fn range(&self) -> (i128, i128) {
+        match self {
+            IntType::I8 => (i8::MIN as i128, i8::MAX as i128),
+            IntType::I16 => (i16::MIN as i128, i16::MAX as i128),
+            IntType::I32 => (i32::MIN as i128, i32::MAX as i128),
+            IntType::I64 | IntType::I128 | IntType::Isize => (i64::MIN as i128, i64::MAX as i128),
+            IntType::U8 => (0, u8::MAX as i128),
+            IntType::U16 => (0, u16::MAX as i128),
+            IntType::U32 => (0, u32::MAX as i128),
+            IntType::U64 | IntType::U128 | IntType::Usize => (0, i64::MAX as i128),
+        }
+    }
+
+    fn ident(&self) -> syn::Ident {
+        syn::Ident::new(self.name(), Span::call_site())
+    }
+
+    /// Suffixed integer literal, e.g. `8080u16`.
+    ///
+    /// The macro runs on the host, so `isize` and `usize` values outside the 16 bit range are
+    /// checked by a const assertion that names `key` if they do not fit on the target.
+    fn literal(&self, key: &str, value: i64) -> pm2::TokenStream {
+        let (wide, min, max) = match self {
+            IntType::Isize => (quote! { i64 }, i16::MIN as i64, i16::MAX as i64),
+            IntType::Usize => (quote! { u64 }, 0, u16::MAX as i64),
+            _ => (quote! {}, i64::MIN, i64::MAX),
+        };
+
+        if (min..=max).contains(&value) {
+            let lit = syn::LitInt::new(&format!("{}{}", value, self.name()), Span::call_site());
+            return quote! { #lit };
+        }
+
+        let ty = self.ident();
+        let lit = syn::LitInt::new(&format!("{}{}", value, wide), Span::call_site());
+        let msg = format!("{}: {} does not fit in {} on this target", key, value, ty);
+        quote! {
+            {
+                const VALUE: #wide = #lit;
+                const _: () = assert!(
+                    VALUE >= #ty::MIN as #wide && VALUE <= #ty::MAX as #wide,
+                    #msg
+                );
+                VALUE as #ty
+            }
+        }
+    }
+}
+
+impl ValueType {
+    /// Parse a type override from a rust type, e.g. `u16` or `core::num::NonZeroUsize`.
+    pub fn from_type(ty: &syn::Type) -> Result<Self, syn::Error> {
+        let error = || {
+            syn::Error::new(
+                ty.span(),
+                "unsupported type override, expected a primitive integer, float, NonZero integer, \
+                 Duration, ByteSize, IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, Version, Rgb, Rgba, \
+                 &CStr or &[u8]",
+            )
+        };
+
+        // string types, e.g. `&'static CStr` or `&[u8]`
+        let ty = match ty {
+            syn::Type::Reference(reference) if reference.mutability.is_none() => &reference.elem,
+            ty => ty,
+        };
+        if let syn::Type::Slice(slice) = ty {
+            return match &*slice.elem {
+                syn::Type::Path(elem) if elem.qself.is_none() && elem.path.is_ident("u8") => {
+                    Ok(ValueType::ByteStr)
+                }
+                _ => Err(error()),
+            };
+        }
+
+        let segment = match ty {
+            syn::Type::Path(type_path) if type_path.qself.is_none() => {
+                type_path.path.segments.last().ok_or_else(error)?
+            }
+            _ => return Err(error()),
+        };
+        let name = segment.ident.to_string();
+
+        // NonZero<T>
+        if name == "NonZero" {
+            let inner = match &segment.arguments {
+                syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => {
+                    match &args.args[0] {
+                        syn::GenericArgument::Type(inner) => Self::from_type(inner)?,
+                        _ => return Err(error()),
+                    }
+                }
+                _ => return Err(error()),
+            };
+
+            return match inner {
+                ValueType::Int(int) => Ok(ValueType::NonZero(int)),
+                _ => Err(error()),
+            };
+        }
+
+        if !segment.arguments.is_empty() {
+            return Err(error());
+        }
+
+        match name.as_str() {
+            "f32" => return Ok(ValueType::F32),
+            "f64" => return Ok(ValueType::F64),
+            "Duration" => return Ok(ValueType::Duration),
+            "ByteSize" => return Ok(ValueType::ByteSize),
+            "IpAddr" => return Ok(ValueType::IpAddr),
+            "Ipv4Addr" => return Ok(ValueType::Ipv4Addr),
+            "Ipv6Addr" => return Ok(ValueType::Ipv6Addr),
+            "SocketAddr" => return Ok(ValueType::SocketAddr),
+            "Version" => return Ok(ValueType::Version),
+            "Rgb" => return Ok(ValueType::Rgb),
+            "Rgba" => return Ok(ValueType::Rgba),
+            "CStr" => return Ok(ValueType::CStr),
+            _ => (),
+        }
+
+        IntType::ALL
+            .iter()
+            .find_map(|int| {
+                if int.name() == name {
+                    Some(ValueType::Int(*int))
+                } else if int.non_zero_name() == name {
+                    Some(ValueType::NonZero(*int))
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(error)
+    }
+
+    /// Returns true if integer values are promoted to this type.
+    pub fn is_float(&self) -> bool {
+        matches!(self, ValueType::F32 | ValueType::F64)
+    }
+
+    /// Check that a value can be generated as this type.
+    pub fn check(&self, value: &toml::Value) -> Result<(), String> {
+        match (self, value) {
+            (ValueType::Int(int) | ValueType::NonZero(int), toml::Value::Integer(i)) => {
+                let (min, max) = int.range();
+
+                if (*i as i128) < min || (*i as i128) > max {
+                    Err(format!("{} does not fit in {}", i, self))
+                } else if *i == 0 && matches!(self, ValueType::NonZero(_)) {
+                    Err(format!("0 does not fit in {}", self))
+                } else {
+                    Ok(())
+                }
+            }
+            (ValueType::F32, toml::Value::Float(f)) => {
+                match f.is_finite() && f.abs() > f32::MAX as f64 {
+                    true => Err(format!("{} does not fit in {}", f, self)),
+                    false => Ok(()),
+                }
+            }
+            (ValueType::F32 | ValueType::F64, toml::Value::Integer(_) | toml::Value::Float(_)) => {
+                Ok(())
+            }
+            (ValueType::Duration, toml::Value::String(s)) => parse_duration(s).map(|_| ()),
+            (ValueType::ByteSize, toml::Value::String(s)) => parse_byte_size(s).map(|_| ()),
+            (ValueType::ByteSize, toml::Value::Integer(i)) => match *i < 0 {
+                true => Err(format!("{} is not a valid byte size", i)),
+                false => Ok(()),
+            },
+            (ValueType::IpAddr, toml::Value::String(s)) => parse_ip_addr(s).map(|_| ()),
+            (ValueType::Ipv4Addr, toml::Value::String(s)) => parse_ipv4_addr(s).map(|_| ()),
+            (ValueType::Ipv6Addr, toml::Value::String(s)) => parse_ipv6_addr(s).map(|_| ()),
+            (ValueType::SocketAddr, toml::Value::String(s)) => parse_socket_addr(s).map(|_| ()),
+            (ValueType::Version, toml::Value::String(s)) => parse_version(s).map(|_| ()),
+            (ValueType::Rgb, toml::Value::String(s)) => parse_color(s, false).map(|_| ()),
+            (ValueType::Rgba, toml::Value::String(s)) => parse_color(s, true).map(|_| ()),
+            (ValueType::Embed(kind), toml::Value::String(path)) => check_embedded(path, *kind),
+            (ValueType::CStr, toml::Value::String(s)) => {
+                std::ffi::CString::new(s.as_str()).map(|_| ()).map_err(|e| {
+                    format!(
+                        "{:?} contains a NUL byte at position {}",
+                        s,
+                        e.nul_position()
+                    )
+                })
+            }
+            (ValueType::ByteStr, toml::Value::String(_)) => Ok(()),
+            // paths are checked when they are resolved
+            (ValueType::Path(_), toml::Value::String(_)) => Ok(()),
+            (ValueType::Duration, _) => Err(format!(
+                "expected a string like \"1h30m\" for Duration, found {}",
+                value.type_str()
+            )),
+            (ValueType::ByteSize, _) => Err(format!(
+                "expected an integer or a string like \"512KiB\" for ByteSize, found {}",
+                value.type_str()
+            )),
+            (ValueType::Int(_) | ValueType::NonZero(_), _) => Err(format!(
+                "expected an integer for {}, found {}",
+                self,
+                value.type_str()
+            )),
+            (ValueType::F32 | ValueType::F64, _) => Err(format!(
+                "expected a number for {}, found {}",
+                self,
+                value.type_str()
+            )),
+            (_, _) => Err(format!(
+                "expected a string for {}, found {}",
+                self,
+                value.type_str()
+            )),
+        }
+    }
+
+    /// Rust type of this value type.
+    pub fn ty(&self, runtime_path: &syn::Path) -> pm2::TokenStream {
+        match self {
+            ValueType::Int(int) => {
+                let ident = int.ident();
+                quote! { #ident }
+            }
+            ValueType::NonZero(int) => {
+                let ident = syn::Ident::new(&int.non_zero_name(), Span::call_site());
+                quote! { ::core::num::#ident }
+            }
+            ValueType::F32 => quote! { f32 },
+            ValueType::F64 => quote! { f64 },
+            ValueType::Duration => quote! { ::core::time::Duration },
+            ValueType::ByteSize => quote! { u64 },
+            ValueType::IpAddr => quote! { ::core::net::IpAddr },
+            ValueType::Ipv4Addr => quote! { ::core::net::Ipv4Addr },
+            ValueType::Ipv6Addr => quote! { ::core::net::Ipv6Addr },
+            ValueType::SocketAddr => quote! { ::core::net::SocketAddr },
+            ValueType::Version => quote! { #runtime_path::Version },
+            ValueType::Rgb => quote! { #runtime_path::Rgb },
+            ValueType::Rgba => quote! { #runtime_path::Rgba },
+            ValueType::Embed(EmbedKind::Bytes) | ValueType::ByteStr => quote! { &'static [u8] },
+            ValueType::CStr => quote! { &'static ::core::ffi::CStr },
+            ValueType::Embed(EmbedKind::Str) | ValueType::Path(_) => quote! { &'static str },
+        }
+    }
+
+    /// Instantiate a value as this type.
+    ///
+    /// Values are checked with [ValueType::check] first.
+    pub fn instantiate(
+        &self,
+        key: &str,
+        value: &toml::Value,
+        runtime_path: &syn::Path,
+    ) -> Result<pm2::TokenStream, String> {
+        // missing keys are empty
+        if let (ValueType::Embed(kind), toml::Value::String(path)) = (self, value) {
+            if path.is_empty() {
+                return Ok(match kind {
+                    EmbedKind::Bytes => quote! { &[] },
+                    EmbedKind::Str => quote! { "" },
+                });
+            }
+        }
+
+        self.check(value)?;
+
+        let tokens = match (self, value) {
+            (ValueType::Int(int), toml::Value::Integer(i)) => int.literal(key, *i),
+            (ValueType::NonZero(int), toml::Value::Integer(i)) => {
+                let ty = self.ty(runtime_path);
+                let lit = int.literal(key, *i);
+
+                // inline consts can be promoted to `'static` in slices and maps
+                quote! {
+                    const {
+                        match #ty::new(#lit) {
+                            Some(non_zero) => non_zero,
+                            None => panic!("value checked to be non-zero"),
+                        }
+                    }
+                }
+            }
+            (ValueType::F32, toml::Value::Integer(i)) => {
+                let lit = pm2::Literal::f32_suffixed(*i as f32);
+                quote! { #lit }
+            }
+            (ValueType::F32, toml::Value::Float(f)) => non_finite_float(*f, quote! { f32 })
+                .unwrap_or_else(|| {
+                    let lit = pm2::Literal::f32_suffixed(*f as f32);
+                    quote! { #lit }
+                }),
+            (ValueType::F64, toml::Value::Integer(i)) => {
+                let lit = pm2::Literal::f64_suffixed(*i as f64);
+                quote! { #lit }
+            }
+            (ValueType::F64, toml::Value::Float(f)) => non_finite_float(*f, quote! { f64 })
+                .unwrap_or_else(|| {
+                    let lit = pm2::Literal::f64_suffixed(*f);
+                    quote! { #lit }
+                }),
+            (ValueType::Duration, toml::Value::String(s)) => {
+                let nanos = parse_duration(s)?;
+                quote! { ::core::time::Duration::from_nanos(#nanos) }
+            }
+            (ValueType::ByteSize, toml::Value::String(s)) => {
+                let bytes = parse_byte_size(s)?;
+                quote! { #bytes }
+            }
+            (ValueType::ByteSize, toml::Value::Integer(i)) => IntType::U64.literal(key, *i),
+            (ValueType::IpAddr, toml::Value::String(s)) => ip_addr_tokens(parse_ip_addr(s)?),
+            (ValueType::Ipv4Addr, toml::Value::String(s)) => ipv4_addr_tokens(parse_ipv4_addr(s)?),
+            (ValueType::Ipv6Addr, toml::Value::String(s)) => ipv6_addr_tokens(parse_ipv6_addr(s)?),
+            (ValueType::SocketAddr, toml::Value::String(s)) => {
+                let (variant, addr) = match parse_socket_addr(s)? {
+                    std::net::SocketAddr::V4(addr) => {
+                        let ip = ipv4_addr_tokens(*addr.ip());
+                        let port = addr.port();
+                        let addr = quote! { ::core::net::SocketAddrV4::new(#ip, #port) };
+                        (quote! { V4 }, addr)
+                    }
+                    std::net::SocketAddr::V6(addr) => {
+                        let ip = ipv6_addr_tokens(*addr.ip());
+                        let (port, flowinfo, scope_id) =
+                            (addr.port(), addr.flowinfo(), addr.scope_id());
+                        let addr = quote! {
+                            ::core::net::SocketAddrV6::new(#ip, #port, #flowinfo, #scope_id)
+                        };
+                        (quote! { V6 }, addr)
+                    }
+                };
+
+                quote! { ::core::net::SocketAddr::#variant(#addr) }
+            }
+            (ValueType::Version, toml::Value::String(s)) => {
+                let Version {
+                    major,
+                    minor,
+                    patch,
+                    pre,
+                    build,
+                } = parse_version(s)?;
+
+                quote! {
+                    #runtime_path::Version {
+                        major: #major,
+                        minor: #minor,
+                        patch: #patch,
+                        pre: #pre,
+                        build: #build,
+                    }
+                }
+            }
+            (ValueType::Rgb, toml::Value::String(s)) => {
+                let [r, g, b, _] = parse_color(s, false)?;
+                quote! { #runtime_path::Rgb { r: #r, g: #g, b: #b } }
+            }
+            (ValueType::Rgba, toml::Value::String(s)) => {
+                let [r, g, b, a] = parse_color(s, true)?;
+                quote! { #runtime_path::Rgba { r: #r, g: #g, b: #b, a: #a } }
+            }
+            (ValueType::Embed(EmbedKind::Bytes), toml::Value::String(path)) => {
+                quote! { include_bytes!(#path) as &[u8] }
+            }
+            (ValueType::Embed(EmbedKind::Str), toml::Value::String(path)) => {
+                quote! { include_str!(#path) }
+            }
+            (ValueType::Path(_), toml::Value::String(path)) => quote! { #path },
+            (ValueType::CStr, toml::Value::String(s)) => {
+                let c_string = std::ffi::CString::new(s.as_str()).expect("value already checked");
+                let lit = pm2::Literal::c_string(&c_string);
+                quote! { #lit }
+            }
+            (ValueType::ByteStr, toml::Value::String(s)) => {
+                let lit = pm2::Literal::byte_string(s.as_bytes());
+                quote! { #lit }
+            }
+            _ => unreachable!("value already checked"),
+        };
+
+        Ok(tokens)
+    }
+
+    /// Default value for keys that are missing from some tables.
+    pub fn default_value(&self) -> toml::Value {
+        match self {
+            ValueType::Int(_) => toml::Value::Integer(0),
+            // this default is rejected when instantiated
+            ValueType::NonZero(_) => toml::Value::Integer(0),
+            ValueType::F32 | ValueType::F64 => toml::Value::Float(0.0),
+            ValueType::Duration => toml::Value::String("0s".to_string()),
+            ValueType::ByteSize => toml::Value::Integer(0),
+            ValueType::IpAddr | ValueType::Ipv4Addr => toml::Value::String("0.0.0.0".to_string()),
+            ValueType::Ipv6Addr => toml::Value::String("::".to_string()),
+            ValueType::SocketAddr => toml::Value::String("0.0.0.0:0".to_string()),
+            ValueType::Version => toml::Value::String("0.0.0".to_string()),
+            ValueType::Rgb => toml::Value::String("#000000".to_string()),
+            ValueType::Rgba => toml::Value::String("#00000000".to_string()),
+            // instantiated as empty contents
+            ValueType::Embed(_) | ValueType::Path(_) | ValueType::CStr | ValueType::ByteStr => {
+                toml::Value::String(String::new())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueType::Int(int) => write!(f, "{}", int.name()),
+            ValueType::NonZero(int) => write!(f, "{}", int.non_zero_name()),
+            ValueType::F32 => write!(f, "f32"),
+            ValueType::F64 => write!(f, "f64"),
+            ValueType::Duration => write!(f, "Duration"),
+            ValueType::ByteSize => write!(f, "ByteSize"),
+            ValueType::IpAddr => write!(f, "IpAddr"),
+            ValueType::Ipv4Addr => write!(f, "Ipv4Addr"),
+            ValueType::Ipv6Addr => write!(f, "Ipv6Addr"),
+            ValueType::SocketAddr => write!(f, "SocketAddr"),
+            ValueType::Version => write!(f, "Version"),
+            ValueType::Rgb => write!(f, "Rgb"),
+            ValueType::Rgba => write!(f, "Rgba"),
+            ValueType::Embed(EmbedKind::Bytes) => write!(f, "&[u8]"),
+            ValueType::Embed(EmbedKind::Str) => write!(f, "&str"),
+            ValueType::Path(_) => write!(f, "path"),
+            ValueType::CStr => write!(f, "&CStr"),
+            ValueType::ByteStr => write!(f, "&[u8]"),
+        }
+    }
+}
+
+/// Parse a decimal number at the start of a string, e.g. `1.5`.
+///
+/// Returns the number as a fraction `(numerator, denominator)` and the rest of the string.
+fn parse_number(s: &str) -> Option<((u128, u128), &str)> {
+    let end = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+        .unwrap_or(s.len());
+    let (number, rest) = s.split_at(end);
+    let number = number.replace('_', "");
+
+    let (int, frac) = match number.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (number.as_str(), ""),
+    };
+    if int.is_empty() || frac.contains('.') || number.ends_with('.') {
+        return None;
+    }
+
+    let denominator = 10u128.checked_pow(frac.len() as u32)?;
+    let numerator = format!("{}{}", int, frac).parse::<u128>().ok()?;
+
+    Some(((numerator, denominator), rest))
+}
+
+/// Parse a duration like `"1h30m"` or `"1.5s"` into nanoseconds.
+///
+/// Durations are limited to `u64::MAX` nanoseconds (about 584 years), so that they can be
+/// constructed with `Duration::from_nanos`.
+fn parse_duration(s: &str) -> Result<u64, String> {
+    let error = || {
+        format!(
+            "\"{}\" is not a valid duration, expected e.g. \"1h30m\" or \"250ms\"",
+            s
+        )
+    };
+
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return Err(error());
+    }
+
+    let mut nanos: u128 = 0;
+    while !rest.is_empty() {
+        let ((numerator, denominator), after) = parse_number(rest).ok_or_else(error)?;
+        let after = after.trim_start();
+        let unit_end = after
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_end);
+
+        let (_, unit_nanos) = DURATION_UNITS
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .ok_or_else(error)?;
+
+        nanos = numerator
+            .checked_mul(*unit_nanos)
+            .map(|n| n / denominator)
+            .and_then(|n| nanos.checked_add(n))
+            .ok_or_else(error)?;
+        rest = after.trim_start();
+    }
+
+    u64::try_from(nanos).map_err(|_| format!("\"{}\" does not fit in Duration", s))
+}
+
+/// Parse a byte size like `"512KiB"` or `"10 MB"` into bytes.
+fn parse_byte_size(s: &str) -> Result<u64, String> {
+    let error = || {
+        format!(
+            "\"{}\" is not a valid byte size, expected e.g. \"512KiB\" or \"10MB\"",
+            s
+        )
+    };
+
+    let ((numerator, denominator), unit) = parse_number(s.trim()).ok_or_else(error)?;
+    let unit = unit.trim_start().to_lowercase();
+
+    let multiplier = match unit.as_str() {
+        "" => 1,
+        unit => {
+            BYTE_UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .ok_or_else(error)?
+                .1
+        }
+    };
+
+    numerator
+        .checked_mul(multiplier)
+        .map(|bytes| bytes / denominator)
+        .and_then(|bytes| u64::try_from(bytes).ok())
+        .ok_or_else(|| format!("\"{}\" does not fit in u64", s))
+}
+
+/// Non-finite floats do not have literals, and are generated as constants of a float type,
+/// e.g. `f64::NAN`. Returns `None` for finite floats.
+pub fn non_finite_float(value: f64, ty: pm2::TokenStream) -> Option<pm2::TokenStream> {
+    if value.is_nan() {
+        Some(quote! { #ty::NAN })
+    } else if value == f64::INFINITY {
+        Some(quote! { #ty::INFINITY })
+    } else if value == f64::NEG_INFINITY {
+        Some(quote! { #ty::NEG_INFINITY })
+    } else {
+        None
+    }
+}
+
+/// Check that an embedded file exists, and that it is valid UTF-8 if it is included as a string.
+fn check_embedded(path: &str, kind: EmbedKind) -> Result<(), String> {
+    let contents = std::fs::read(path).map_err(|e| format!("cannot embed {}: {}", path, e))?;
+
+    match kind {
+        EmbedKind::Str if std::str::from_utf8(&contents).is_err() => Err(format!(
+            "cannot embed {} as a string: file is not valid UTF-8",
+            path
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn parse_ip_addr(s: &str) -> Result<std::net::IpAddr, String> {
+    s.parse()
+        .map_err(|_| format!("\"{}\" is not a valid IP address", s))
+}
+
+fn parse_ipv4_addr(s: &str) -> Result<std::net::Ipv4Addr, String> {
+    s.parse()
+        .map_err(|_| format!("\"{}\" is not a valid IPv4 address", s))
+}
+
+fn parse_ipv6_addr(s: &str) -> Result<std::net::Ipv6Addr, String> {
+    s.parse()
+        .map_err(|_| format!("\"{}\" is not a valid IPv6 address", s))
+}
+
+fn parse_socket_addr(s: &str) -> Result<std::net::SocketAddr, String> {
+    s.parse().map_err(|_| {
+        format!(
+            "\"{}\" is not a valid socket address, expected e.g. \"127.0.0.1:8080\"",
+            s
+        )
+    })
+}
+
+fn ip_addr_tokens(ip: std::net::IpAddr) -> pm2::TokenStream {
+    match ip {
+        std::net::IpAddr::V4(ip) => {
+            let ip = ipv4_addr_tokens(ip);
+            quote! { ::core::net::IpAddr::V4(#ip) }
+        }
+        std::net::IpAddr::V6(ip) => {
+            let ip = ipv6_addr_tokens(ip);
+            quote! { ::core::net::IpAddr::V6(#ip) }
+        }
+    }
+}
+
+fn ipv4_addr_tokens(ip: std::net::Ipv4Addr) -> pm2::TokenStream {
+    let octets = ip.octets();
+    quote! { ::core::net::Ipv4Addr::new(#(#octets),*) }
+}
+
+fn ipv6_addr_tokens(ip: std::net::Ipv6Addr) -> pm2::TokenStream {
+    let segments = ip.segments();
+    quote! { ::core::net::Ipv6Addr::new(#(#segments),*) }
+}
+
+/// Parse a semantic version, e.g. `"1.2.3-beta.1+build.5"`.
+fn parse_version(s: &str) -> Result<Version<'_>, String> {
+    let error = || {
+        format!(
+            "\"{}\" is not a valid semantic version, expected e.g. \"1.2.3\" or \"1.2.3-beta\"",
+            s
+        )
+    };
+
+    let (rest, build) = s.split_once('+').unwrap_or((s, ""));
+    let (core, pre) = rest.split_once('-').unwrap_or((rest, ""));
+
+    let number = |n: &str| match n.len() > 1 && n.starts_with('0') {
+        true => None,
+        false => n.parse::<u64>().ok(),
+    };
+    let mut numbers = core.split('.').map(number);
+    let (major, minor, patch) = match (
+        numbers.next().flatten(),
+        numbers.next().flatten(),
+        numbers.next().flatten(),
+        numbers.next(),
+    ) {
+        (Some(major), Some(minor), Some(patch), None) => (major, minor, patch),
+        _ => return Err(error()),
+    };
+
+    let valid_identifiers = |ids: &str, is_pre: bool| {
+        ids.split('.').all(|id| {
+            !id.is_empty()
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                // numeric pre-release identifiers do not have leading zeros
+                && !(is_pre && id.len() > 1 && id.starts_with('0') && id.chars().all(|c| c.is_ascii_digit()))
+        })
+    };
+    let has_pre = rest.contains('-');
+    let has_build = s.contains('+');
+    if (has_pre && !valid_identifiers(pre, true)) || (has_build && !valid_identifiers(build, false))
+    {
+        return Err(error());
+    }
+
+    Ok(Version {
+        major,
+        minor,
+        patch,
+        pre,
+        build,
+    })
+}
+
+/// Parse a hex color, e.g. `"#ff8800"` or `"#f80"`.
+///
+/// With `alpha`, colors can also have an alpha channel, e.g. `"#ff880080"`.
+/// The alpha channel defaults to `0xff`.
+fn parse_color(s: &str, alpha: bool) -> Result<[u8; 4], String> {
+    let error = || match alpha {
+        true => format!(
+            "\"{}\" is not a valid color, expected e.g. \"#ff8800\" or \"#ff880080\"",
+            s
+        ),
+        false => format!("\"{}\" is not a valid color, expected e.g. \"#ff8800\"", s),
+    };
+
+    let hex = s.strip_prefix('#').ok_or_else(error)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+
+    // shorthand colors repeat each digit, e.g. `f80` is `ff8800`
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+        _ => hex.to_string(),
+    };
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).expect("hex digits");
+
+    match hex.len() {
+        6 => Ok([channel(0), channel(1), channel(2), 0xff]),
+        8 if alpha => Ok([channel(0), channel(1), channel(2), channel(3)]),
+        _ => Err(error()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value_types() {
+        let parse = |ty: syn::Type| ValueType::from_type(&ty);
+
+        assert_eq!(
+            parse(syn::parse_quote!(u16)).unwrap(),
+            ValueType::Int(IntType::U16)
+        );
+        assert_eq!(parse(syn::parse_quote!(f32)).unwrap(), ValueType::F32);
+        assert_eq!(
+            parse(syn::parse_quote!(core::num::NonZeroUsize)).unwrap(),
+            ValueType::NonZero(IntType::Usize)
+        );
+        assert_eq!(
+            parse(syn::parse_quote!(NonZero<i8>)).unwrap(),
+            ValueType::NonZero(IntType::I8)
+        );
+        assert_eq!(
+            parse(syn::parse_quote!(core::time::Duration)).unwrap(),
+            ValueType::Duration
+        );
+        assert_eq!(
+            parse(syn::parse_quote!(ByteSize)).unwrap(),
+            ValueType::ByteSize
+        );
+        assert_eq!(
+            parse(syn::parse_quote!(&'static core::ffi::CStr)).unwrap(),
+            ValueType::CStr
+        );
+        assert_eq!(parse(syn::parse_quote!(&[u8])).unwrap(), ValueType::ByteStr);
+        assert!(parse(syn::parse_quote!(&[u16])).is_err());
+        assert!(parse(syn::parse_quote!(String)).is_err());
+        assert!(parse(syn::parse_quote!(NonZero<f32>)).is_err());
+        assert!(parse(syn::parse_quote!(&'static str)).is_err());
+        assert!(parse(syn::parse_quote!(&mut [u8])).is_err());
+    }
+
+    #[test]
+    fn test_check_value_types() {
+        let u16_ty = ValueType::Int(IntType::U16);
+        assert!(u16_ty.check(&toml::Value::Integer(8080)).is_ok());
+        assert!(u16_ty.check(&toml::Value::Integer(70000)).is_err());
+        assert!(u16_ty.check(&toml::Value::Integer(-1)).is_err());
+        assert!(u16_ty.check(&toml::Value::Float(1.0)).is_err());
+
+        let non_zero = ValueType::NonZero(IntType::Usize);
+        assert!(non_zero.check(&toml::Value::Integer(4)).is_ok());
+        assert!(non_zero.check(&toml::Value::Integer(0)).is_err());
+
+        assert!(ValueType::F32.check(&toml::Value::Float(0.5)).is_ok());
+        assert!(ValueType::F32.check(&toml::Value::Integer(2)).is_ok());
+        assert!(ValueType::F32.check(&toml::Value::Float(1e300)).is_err());
+        assert!(ValueType::F32
+            .check(&toml::Value::Float(f64::INFINITY))
+            .is_ok());
+        assert!(ValueType::F32
+            .check(&toml::Value::String("1".to_string()))
+            .is_err());
+
+        assert_eq!(
+            u16_ty
+                .instantiate(
+                    "port",
+                    &toml::Value::Integer(8080),
+                    &syn::parse_quote!(::toml_const)
+                )
+                .unwrap()
+                .to_string(),
+            "8080u16"
+        );
+
+        // large pointer-sized values are checked on the target
+        let usize_ty = ValueType::Int(IntType::Usize);
+        assert_eq!(
+            usize_ty
+                .instantiate(
+                    "size",
+                    &toml::Value::Integer(65535),
+                    &syn::parse_quote!(::toml_const)
+                )
+                .unwrap()
+                .to_string(),
+            "65535usize"
+        );
+        let large = usize_ty
+            .instantiate(
+                "size",
+                &toml::Value::Integer(5_000_000_000),
+                &syn::parse_quote!(::toml_const),
+            )
+            .unwrap()
+            .to_string();
+        assert!(large.contains("5000000000u64"));
+        assert!(large.contains("\"size: 5000000000 does not fit in usize on this target\""));
+    }
+
+    #[test]
+    fn test_parse_units() {
+        assert_eq!(parse_duration("1h30m"), Ok(90 * 60 * 1_000_000_000));
+        assert_eq!(parse_duration("250ms"), Ok(250_000_000));
+        assert_eq!(parse_duration("1.5s"), Ok(1_500_000_000));
+        assert_eq!(parse_duration("1m 30s"), Ok(90_000_000_000));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("1x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1000y").is_err());
+        assert!(parse_duration("600000000000h").is_err());
+
+        assert_eq!(parse_byte_size("512KiB"), Ok(512 * 1024));
+        assert_eq!(parse_byte_size("10MB"), Ok(10_000_000));
+        assert_eq!(parse_byte_size("1.5 GiB"), Ok(3 << 29));
+        assert_eq!(parse_byte_size("100"), Ok(100));
+        assert!(parse_byte_size("10 parsecs").is_err());
+        assert!(parse_byte_size("1.2.3MB").is_err());
+        assert!(parse_byte_size("100000PiB").is_err());
+
+        assert_eq!(
+            ValueType::Duration
+                .instantiate(
+                    "timeout",
+                    &toml::Value::String("1.5s".to_string()),
+                    &syn::parse_quote!(::toml_const)
+                )
+                .unwrap()
+                .to_string(),
+            quote! { ::core::time::Duration::from_nanos(1500000000u64) }.to_string()
+        );
+        assert!(ValueType::Duration
+            .check(&toml::Value::Integer(30))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_strings() {
+        assert!(parse_ip_addr("::1").is_ok());
+        assert!(parse_ipv4_addr("::1").is_err());
+        assert!(parse_socket_addr("[::1]:8080").is_ok());
+        assert!(parse_socket_addr("127.0.0.1").is_err());
+
+        assert_eq!(
+            parse_version("1.2.3-beta.1+build.5"),
+            Ok(Version {
+                major: 1,
+                minor: 2,
+                patch: 3,
+                pre: "beta.1",
+                build: "build.5"
+            })
+        );
+        assert!(parse_version("1.2.3-rc-1").is_ok());
+        assert!(parse_version("1.2").is_err());
+        assert!(parse_version("1.2.3.4").is_err());
+        assert!(parse_version("01.2.3").is_err());
+        assert!(parse_version("1.2.3-").is_err());
+        assert!(parse_version("1.2.3-beta.01").is_err());
+        assert!(parse_version("1.2.3+").is_err());
+
+        assert_eq!(parse_color("#ff8800", false), Ok([0xff, 0x88, 0x00, 0xff]));
+        assert_eq!(parse_color("#f80", false), Ok([0xff, 0x88, 0x00, 0xff]));
+        assert_eq!(parse_color("#ff880080", true), Ok([0xff, 0x88, 0x00, 0x80]));
+        assert!(parse_color("#ff880080", false).is_err());
+        assert!(parse_color("ff8800", false).is_err());
+        assert!(parse_color("#gg8800", false).is_err());
+
+        let runtime_path: syn::Path = syn::parse_quote!(toml_const);
+        assert_eq!(
+            ValueType::Rgb
+                .instantiate(
+                    "color",
+                    &toml::Value::String("#ff8800".to_string()),
+                    &runtime_path
+                )
+                .unwrap()
+                .to_string(),
+            quote! { toml_const::Rgb { r: 255u8, g: 136u8, b: 0u8 } }.to_string()
+        );
+        assert!(ValueType::SocketAddr
+            .check(&toml::Value::Integer(8080))
+            .is_err());
+
+        assert!(ValueType::CStr
+            .check(&toml::Value::String("lib\0c".to_string()))
+            .is_err());
+        assert_eq!(
+            ValueType::CStr
+                .instantiate(
+                    "lib",
+                    &toml::Value::String("libc".to_string()),
+                    &runtime_path
+                )
+                .unwrap()
+                .to_string(),
+            "c\"libc\""
+        );
+    }
+}
@@ -0,0 +1,18 @@
+toml_const::toml_const! {
+    pub const CONFIG: "config.toml";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CONFIG;
+
+    #[test]
+    fn table_maps_use_const_map_without_phf() {
+        const HTTPS: Option<&i64> = CONFIG.ports.const_map().get("https");
+
+        assert_eq!(CONFIG.name, "test");
+        assert_eq!(HTTPS, Some(&443));
+        assert_eq!(CONFIG.servers.const_map().get("beta").unwrap().port, 8081);
+        assert_eq!(CONFIG.servers.const_map().len(), 2);
+    }
+}
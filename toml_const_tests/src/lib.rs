@@ -21,9 +21,237 @@ toml_const::toml_const! {
     const NORMALIZE_TOML: "../normalize.toml";
 }
 
+toml_const::toml_const! {
+    #[promote_numbers]
+    const PROMOTE_TOML: "configs/promote.toml";
+}
+
+toml_const::toml_const! {
+    #[mixed_arrays]
+    const MIXED_TOML: "configs/mixed.toml";
+}
+
+toml_const::toml_const! {
+    #[tagged(listeners by kind)]
+    const TAGGED_TOML: "configs/tagged.toml";
+}
+
+toml_const::toml_const! {
+    #[fixed_arrays]
+    const FIXED_TOML: "configs/fixed.toml";
+
+    #[fixed_arrays(matrix, point)]
+    const FIXED_MATRIX_TOML: "configs/fixed.toml";
+}
+
+toml_const::toml_const! {
+    #[ty("server.port" = u16, "limits.ratio" = f32, "pool.size" = NonZeroUsize)]
+    #[ty("pool.limit" = usize)]
+    #[ty(limits.retries = u8, servers.port = u16, servers.weights = f64)]
+    const TYPES_TOML: "configs/types.toml";
+}
+
+toml_const::toml_const! {
+    #[ty("**.*_timeout" = Duration, retry_delays = Duration, cache.ttl = Duration)]
+    #[ty("**.*_size" = ByteSize)]
+    const UNITS_TOML: "configs/units.toml";
+}
+
+toml_const::toml_const! {
+    #[ty("*version" = Version, server.bind = IpAddr, server.dns = Ipv6Addr)]
+    #[ty(server.listen = SocketAddr, server.upstreams = SocketAddr)]
+    #[ty(theme.accent = Rgb, theme.background = Rgb, theme.overlay = Rgba)]
+    const PARSED_TOML: "configs/parsed.toml";
+}
+
+toml_const::toml_const! {
+    #[embed(logo, certs = bytes, "templates.*" = str)]
+    const EMBED_TOML: "configs/embed.toml";
+}
+
+toml_const::toml_const! {
+    #[path(assets, certs, "templates.*" = manifest)]
+    const PATHS_TOML: "configs/paths.toml";
+}
+
+toml_const::toml_const! {
+    #[ty(device = &CStr, symbols = &'static core::ffi::CStr, header.magic = &[u8])]
+    const FFI_TOML: "configs/ffi.toml";
+}
+
+toml_const::toml_const! {
+    #[ty(thresholds = f32, "sensors.*.limit" = f32)]
+    const FLOATS_TOML: "configs/floats.toml";
+}
+
+toml_const::toml_const! {
+    #[rename("'weird key!'" = weird)]
+    const KEYS_TOML: "configs/keys.toml";
+}
+
+toml_const::toml_const! {
+    #[suffix_collisions]
+    const COLLISIONS_TOML: "configs/collisions.toml";
+}
+
+toml_const::toml_const! {
+    #[naming(fields = original, types = upper_camel_case, type_prefix = Config)]
+    const NAMING_TOML: "configs/naming.toml";
+
+    #[naming(type_suffix = Toml)]
+    const DEFAULT_NAMING_TOML: "configs/naming.toml";
+
+    #[naming(fields = original)]
+    const ORIGINAL: "configs/naming.toml";
+}
+
+toml_const::toml_const! {
+    #[derive(PartialEq)]
+    #[shared_types]
+    const SHARED_TOML: "configs/shared.toml";
+}
+
+/// Hand-written types that tables are mapped onto.
+pub mod config {
+    // bare map targets list their fields for the key check
+    #[derive(Clone, Copy, Debug, PartialEq, toml_const::TomlConst)]
+    pub struct ServerConfig {
+        pub host: &'static str,
+        pub port: u16,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Limits {
+        pub min: i64,
+        pub max: i64,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Pool {
+        pub size: usize,
+        pub timeout_secs: u64,
+    }
+
+    impl Pool {
+        pub const fn new(size: u16, timeout: u16) -> Self {
+            Self {
+                size: size as usize,
+                timeout_secs: timeout as u64,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Route {
+        pub path: &'static str,
+        pub target: &'static str,
+    }
+}
+
+toml_const::toml_const! {
+    #[derive(PartialEq)]
+    const SERVERS_TOML: "configs/servers.toml";
+}
+
+toml_const::toml_const! {
+    #[index(products by name, teams by id, teams.members by name)]
+    #[derive(PartialEq)]
+    const INDEX_TOML: "configs/index.toml";
+}
+
+toml_const::toml_const! {
+    #[table_map(servers)]
+    #[no_table_map(settings)]
+    const TABLE_MAPS_TOML: "configs/table_maps.toml";
+}
+
+toml_const::toml_const! {
+    // without a namespace, the generated `config` module would conflict with the one above
+    #[namespace(cfg_types)]
+    pub(crate) const CONFIG: "configs/namespace.toml";
+}
+
+toml_const::toml_const! {
+    #[ty("servers.*.port" = u16, pool.size = u16, pool.timeout = u16)]
+    #[map(
+        "servers.*" = crate::config::ServerConfig,
+        limits = crate::config::Limits { min, max },
+        pool = crate::config::Pool::new(size, timeout),
+        routes = crate::config::Route { path, target },
+    )]
+    const MAPPED_TOML: "configs/mapped.toml";
+}
+
+toml_const::toml_const! {
+    #[derive(PartialEq)]
+    #[enum_values(log_level = [trace, debug, info, warn, error], fallback = [warn, error])]
+    #[enum_values(routes.method)]
+    const ENUMS_TOML: "configs/enums.toml";
+}
+
+toml_const::toml_const! {
+    #[flags(capabilities = [read, write, admin, audit], users.capabilities)]
+    const FLAGS_TOML: "configs/flags.toml";
+}
+
+/// Structs that are the schema of a TOML file.
+pub mod derived {
+    use toml_const::TomlConst;
+
+    #[derive(Debug, TomlConst)]
+    #[toml_const(path = "configs/derive.toml")]
+    pub struct Config {
+        pub name: &'static str,
+        pub port: u16,
+        pub ratio: f32,
+        pub enabled: bool,
+        pub ids: [u8; 3],
+        pub started: toml_const::OffsetDateTime,
+        pub max_rate: f64,
+        pub magic: &'static [u8],
+        pub library: &'static core::ffi::CStr,
+        pub server: Server,
+        pub routes: &'static [Route],
+    }
+
+    #[derive(Debug, TomlConst)]
+    pub struct Server {
+        pub host: &'static str,
+        pub timeout: u32,
+        pub max_load: f32,
+        pub retries: u8,
+        pub interval: core::time::Duration,
+        pub bind: core::net::IpAddr,
+        pub library: &'static core::ffi::CStr,
+        pub started: toml_const::LocalDate,
+        pub octets: [u8; 2],
+        pub tls: Tls,
+    }
+
+    #[derive(Debug, TomlConst)]
+    pub struct Tls {
+        pub cert: &'static str,
+    }
+
+    #[derive(Debug, TomlConst)]
+    pub struct Route {
+        pub path: &'static str,
+        pub weight: f64,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::NORMALIZE_TOML;
+    use crate::{
+        collisions_toml, config, derived, enums_toml, flags_toml, COLLISIONS_TOML,
+        DEFAULT_NAMING_TOML, EMBED_TOML, ENUMS_TOML, FFI_TOML, FLAGS_TOML, FLOATS_TOML, INDEX_TOML,
+        KEYS_TOML, MAPPED_TOML, NAMING_TOML, ORIGINAL, SERVERS_TOML, SHARED_TOML, TABLE_MAPS_TOML,
+    };
+    use crate::{
+        default_naming_toml, fixed_toml, keys_toml, mixed_toml, naming_toml, original, tagged_toml,
+        FIXED_MATRIX_TOML, FIXED_TOML, MIXED_TOML, NORMALIZE_TOML, PARSED_TOML, PATHS_TOML,
+        PROMOTE_TOML, TAGGED_TOML, TYPES_TOML, UNITS_TOML,
+    };
 
     #[test]
     fn test_print_nornalize() {
@@ -44,4 +272,523 @@ mod tests {
         assert!(NORMALIZE_TOML.items.len() > 1);
         assert!(NORMALIZE_TOML.table_map_array.map_array.len() > 1);
     }
+
+    #[test]
+    fn test_table_map_value_types() {
+        use crate::normalize_toml::{array_values, identical_values};
+
+        let _: identical_values::IdenticalValuesValue = NORMALIZE_TOML.identical_values.second;
+        let _: &[array_values::ArrayValuesValue] = NORMALIZE_TOML.array_values.first;
+        assert_eq!(NORMALIZE_TOML.array_values.second[1].name, "c");
+    }
+
+    #[test]
+    fn test_table_map_keys() {
+        use crate::servers_toml::ServersKey;
+
+        const BETA: &str = SERVERS_TOML.servers.get(ServersKey::Beta).host;
+        assert_eq!(BETA, "10.0.0.2");
+
+        // adding or removing a server fails to compile here
+        let port = |key: ServersKey| match key {
+            ServersKey::Alpha => 8080,
+            ServersKey::Beta => 8081,
+        };
+
+        assert_eq!(ServersKey::ALL, &[ServersKey::Alpha, ServersKey::Beta]);
+        for key in ServersKey::ALL {
+            assert_eq!(SERVERS_TOML.servers.get(*key).port, port(*key));
+            assert_eq!(key.as_str().parse::<ServersKey>(), Ok(*key));
+            assert_eq!(
+                SERVERS_TOML.servers.map().get(key.as_str()),
+                Some(SERVERS_TOML.servers.get(*key))
+            );
+        }
+        assert_eq!(ServersKey::Alpha.to_string(), "alpha");
+        assert!("gamma".parse::<ServersKey>().is_err());
+    }
+
+    #[test]
+    fn test_const_map() {
+        const HTTPS: Option<&i64> = SERVERS_TOML.ports.const_map().get("https");
+        assert_eq!(HTTPS, Some(&443));
+        assert_eq!(SERVERS_TOML.ports.const_map().get("ftp"), None);
+        assert_eq!(SERVERS_TOML.ports.const_map().get("http"), Some(&80));
+
+        let ports = SERVERS_TOML.ports.const_map();
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports.keys().collect::<Vec<_>>(), ["dns", "http", "https"]);
+        assert_eq!(ports.entries()[0], ("dns", 53));
+        for (key, value) in ports {
+            assert_eq!(SERVERS_TOML.ports.map().get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_indexed_arrays() {
+        const LAPTOP_PRICE: Option<i64> = match INDEX_TOML.products_by_name().get("Laptop") {
+            Some(product) => Some(product.price),
+            None => None,
+        };
+        assert_eq!(LAPTOP_PRICE, Some(1000));
+
+        // the array keeps its order, the index is sorted by key
+        assert_eq!(INDEX_TOML.products[0].name, "Tablet");
+        let products = INDEX_TOML.products_by_name();
+        assert_eq!(products.keys().collect::<Vec<_>>(), ["Laptop", "Tablet"]);
+        assert_eq!(products.get("Tablet"), Some(&INDEX_TOML.products[0]));
+        assert_eq!(products.get("Phone"), None);
+
+        // arrays inside indexed tables are indexed per table
+        let core = INDEX_TOML.teams_by_id().get("core").expect("team exists");
+        assert_eq!(
+            core.members_by_name().get("alan").map(|m| m.role),
+            Some("dev")
+        );
+        let web = INDEX_TOML.teams_by_id().get("web").expect("team exists");
+        assert_eq!(web.members_by_name().len(), 1);
+        assert!(web.members_by_name().get("ada").is_none());
+    }
+
+    #[test]
+    fn test_forced_table_maps() {
+        use crate::table_maps_toml::{servers::ServersValue, ServersKey};
+
+        // keys missing from some values are filled in with defaults
+        let alpha: ServersValue = TABLE_MAPS_TOML.servers.alpha;
+        assert!(!alpha.tls);
+        assert!(TABLE_MAPS_TOML.servers.get(ServersKey::Beta).tls);
+        assert_eq!(
+            TABLE_MAPS_TOML
+                .servers
+                .const_map()
+                .get("beta")
+                .map(|s| s.port),
+            Some(8081)
+        );
+
+        // settings is generated as a struct without map methods
+        let _: crate::table_maps_toml::Settings = TABLE_MAPS_TOML.settings;
+        assert_eq!(TABLE_MAPS_TOML.settings.retries, 3);
+    }
+
+    #[test]
+    fn test_promote_numbers() {
+        assert_eq!(PROMOTE_TOML.values, &[1.0, 2.5, 3.0]);
+        assert_eq!(PROMOTE_TOML.products[0].price, 1000.0);
+        assert_eq!(PROMOTE_TOML.products[1].price, 999.99);
+    }
+
+    #[test]
+    fn test_mixed_arrays() {
+        assert_eq!(MIXED_TOML.args.len(), 4);
+        assert!(matches!(
+            MIXED_TOML.args[0],
+            mixed_toml::ArgsItem::String("--verbose")
+        ));
+        assert!(matches!(
+            MIXED_TOML.args[1],
+            mixed_toml::ArgsItem::Integer(3)
+        ));
+
+        match MIXED_TOML.args[2] {
+            mixed_toml::ArgsItem::Table(table) => {
+                assert_eq!(table.name, "plugin");
+                assert_eq!(table.level, 2);
+            }
+            other => panic!("expected a table, found {:?}", other),
+        }
+
+        match MIXED_TOML.nested[0] {
+            mixed_toml::NestedItem::Array(inner) => {
+                assert!(matches!(inner[0], mixed_toml::NestedArrayItem::Integer(1)));
+                assert!(matches!(
+                    inner[1],
+                    mixed_toml::NestedArrayItem::String("one")
+                ));
+            }
+            other => panic!("expected an array, found {:?}", other),
+        }
+
+        assert!(matches!(
+            MIXED_TOML.plugins[1].arg,
+            mixed_toml::plugins::ArgItem::String("value")
+        ));
+    }
+
+    #[test]
+    fn test_tagged_arrays() {
+        use tagged_toml::Listeners;
+
+        assert_eq!(TAGGED_TOML.listeners.len(), 3);
+
+        for listener in TAGGED_TOML.listeners {
+            match listener {
+                Listeners::Http(http) => assert!(http.port == 8080 || http.port == 8081),
+                Listeners::Grpc(grpc) => assert_eq!(grpc.max_message_size, 4096),
+            }
+        }
+
+        match TAGGED_TOML.listeners[2] {
+            Listeners::Http(http) => assert_eq!(http.path, ""),
+            other => panic!("expected a http listener, found {:?}", other),
+        }
+
+        assert_eq!(TAGGED_TOML.listeners[0].kind(), "http");
+        assert_eq!(TAGGED_TOML.listeners[1].kind(), "grpc");
+    }
+
+    #[test]
+    fn test_fixed_arrays() {
+        const MATRIX: [[f64; 2]; 2] = FIXED_TOML.matrix;
+        const LUT: [i64; 4] = FIXED_TOML.lut;
+        const POINT: (i64, &str, bool) = FIXED_TOML.point;
+        const RAGGED: [&[i64]; 2] = FIXED_TOML.ragged;
+        const EMPTY: [&str; 0] = FIXED_TOML.empty;
+        const SENSORS: [fixed_toml::Sensors; 2] = FIXED_TOML.sensors;
+
+        assert_eq!(MATRIX, [[1.0, 0.0], [0.0, 1.0]]);
+        assert_eq!(LUT, [0, 1, 4, 9]);
+        assert_eq!(POINT, (1, "x", true));
+        assert_eq!(RAGGED[1], &[2, 3]);
+        assert!(EMPTY.is_empty());
+        assert_eq!(SENSORS[1].calibration, [4.0, 5.0, 6.0]);
+
+        // only the selected key is fixed
+        const MATRIX_ONLY: [[f64; 2]; 2] = FIXED_MATRIX_TOML.matrix;
+        const LUT_SLICE: &[i64] = FIXED_MATRIX_TOML.lut;
+        assert_eq!(MATRIX_ONLY, MATRIX);
+        assert_eq!(LUT_SLICE, &LUT);
+    }
+
+    #[test]
+    fn test_type_overrides() {
+        const PORT: u16 = TYPES_TOML.server.port;
+        const RATIO: f32 = TYPES_TOML.limits.ratio;
+        const RETRIES: u8 = TYPES_TOML.limits.retries;
+        const SIZE: core::num::NonZeroUsize = TYPES_TOML.pool.size;
+
+        assert_eq!(PORT, 8080);
+        assert_eq!(RATIO, 0.75);
+        assert_eq!(RETRIES, 3);
+        assert_eq!(SIZE.get(), 16);
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(TYPES_TOML.pool.limit, 1usize << 32);
+
+        assert_eq!(TYPES_TOML.servers[1].port, 8082u16);
+        // integers are accepted for floats, and missing arrays are empty
+        assert_eq!(TYPES_TOML.servers[1].weights, &[1.0, 2.5]);
+        assert!(TYPES_TOML.servers[0].weights.is_empty());
+    }
+
+    #[test]
+    fn test_units() {
+        use core::time::Duration;
+
+        const READ_TIMEOUT: Duration = UNITS_TOML.read_timeout;
+        const MAX_SIZE: u64 = UNITS_TOML.cache.max_size;
+
+        assert_eq!(READ_TIMEOUT, Duration::from_secs(90 * 60));
+        assert_eq!(
+            UNITS_TOML.retry_delays,
+            &[
+                Duration::from_millis(250),
+                Duration::from_millis(1500),
+                Duration::from_secs(90)
+            ]
+        );
+        assert_eq!(UNITS_TOML.server.http.idle_timeout, Duration::from_secs(90));
+        assert_eq!(UNITS_TOML.cache.ttl, Duration::from_secs(7 * 24 * 60 * 60));
+
+        assert_eq!(MAX_SIZE, 512 * 1024);
+        assert_eq!(UNITS_TOML.cache.disk_size, 10_000_000);
+        // plain integers are byte counts
+        assert_eq!(UNITS_TOML.cache.entry_size, 4096);
+    }
+
+    #[test]
+    fn test_parsed_strings() {
+        use core::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+        const BIND: IpAddr = PARSED_TOML.server.bind;
+        const VERSION: toml_const::Version = PARSED_TOML.version;
+
+        assert_eq!(BIND, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(PARSED_TOML.server.dns.segments()[0], 0x2001);
+        assert_eq!(
+            PARSED_TOML.server.listen,
+            "[::1]:8443".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(PARSED_TOML.server.upstreams[1].port(), 8080);
+
+        assert_eq!((VERSION.major, VERSION.minor, VERSION.patch), (1, 4, 0));
+        assert_eq!(VERSION.pre, "rc.1");
+        assert_eq!(VERSION.to_string(), "1.4.0-rc.1+build.7");
+        assert!(VERSION > PARSED_TOML.min_version);
+
+        assert_eq!(
+            PARSED_TOML.theme.accent,
+            toml_const::Rgb {
+                r: 0xff,
+                g: 0x88,
+                b: 0x00
+            }
+        );
+        assert_eq!(PARSED_TOML.theme.background.to_string(), "#ffffff");
+        assert_eq!(PARSED_TOML.theme.overlay.a, 0x80);
+    }
+
+    #[test]
+    fn test_embedded_files() {
+        const LOGO: &[u8] = EMBED_TOML.logo;
+        const GREETING: &str = EMBED_TOML.templates.greeting;
+
+        assert_eq!(LOGO, include_bytes!("../configs/assets/logo.png"));
+        assert_eq!(GREETING, "Hello, {name}!\n");
+        assert_eq!(EMBED_TOML.templates.page, "<h1>{title}</h1>\n");
+        assert!(EMBED_TOML.certs[0].starts_with(b"-----BEGIN CERTIFICATE-----"));
+    }
+
+    #[test]
+    fn test_resolved_paths() {
+        let configs = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("configs")
+            .canonicalize()
+            .unwrap();
+
+        // relative to the TOML file, generated as canonical absolute paths
+        assert_eq!(
+            std::path::Path::new(PATHS_TOML.assets),
+            configs.join("assets")
+        );
+        assert_eq!(
+            std::path::Path::new(PATHS_TOML.certs[1]),
+            configs.join("assets/logo.png")
+        );
+
+        // relative to the manifest, generated as relative paths
+        assert_eq!(PATHS_TOML.templates.dir, "configs/assets/templates");
+        assert_eq!(
+            PATHS_TOML.templates.page,
+            "configs/assets/templates/page.html"
+        );
+    }
+
+    #[test]
+    fn test_c_and_byte_strings() {
+        use core::ffi::CStr;
+
+        const DEVICE: &CStr = FFI_TOML.device;
+        const MAGIC: &[u8] = FFI_TOML.header.magic;
+
+        assert_eq!(DEVICE, c"/dev/ttyUSB0");
+        assert_eq!(DEVICE.to_bytes_with_nul().last(), Some(&0));
+        assert_eq!(FFI_TOML.symbols, &[c"init", c"shutdown"]);
+        assert_eq!(MAGIC, b"\x7fELF");
+        assert_eq!(FFI_TOML.header.name, "firmware");
+    }
+
+    #[test]
+    fn test_non_finite_floats() {
+        const MAX: f64 = FLOATS_TOML.max_temperature;
+
+        assert_eq!(MAX, f64::INFINITY);
+        assert_eq!(FLOATS_TOML.min_temperature, f64::NEG_INFINITY);
+        assert!(FLOATS_TOML.calibration.is_nan());
+        assert_eq!(
+            FLOATS_TOML.thresholds,
+            &[0.5f32, f32::INFINITY, f32::NEG_INFINITY]
+        );
+
+        assert_eq!(FLOATS_TOML.sensors.intake.limit, f32::INFINITY);
+        assert_eq!(FLOATS_TOML.sensors.exhaust.limit, 120.0f32);
+        assert!(FLOATS_TOML.sensors.exhaust.offset.is_nan());
+    }
+
+    #[test]
+    fn test_arbitrary_keys() {
+        assert_eq!(KEYS_TOML.key_, "empty");
+        assert_eq!(KEYS_TOML.key_2b, "plus");
+        assert_eq!(KEYS_TOML.café, "unicode");
+        assert_eq!(KEYS_TOML.名前, "name");
+        assert_eq!(KEYS_TOML.weird, 1);
+        assert_eq!(KEYS_TOML.__map__, "no longer reserved");
+
+        assert_eq!(KEYS_TOML.self_, "self");
+        assert_eq!(KEYS_TOML.super_, "super");
+        assert_eq!(KEYS_TOML.crate_, "crate");
+
+        let _: keys_toml::modules::ModulesValue = KEYS_TOML.modules.self_;
+        assert_eq!(KEYS_TOML.modules.super_.value, 2);
+        assert_eq!(
+            KEYS_TOML.modules.map().get("Self").map(|m| m.value),
+            Some(1)
+        );
+
+        assert_eq!(KEYS_TOML.limits.__map__, 1);
+        assert_eq!(KEYS_TOML.limits.map().get("__map__"), Some(&1));
+        assert_eq!(KEYS_TOML.limits.map().len(), 2);
+    }
+
+    #[test]
+    fn test_suffix_collisions() {
+        assert_eq!(COLLISIONS_TOML.a_b, 1);
+        assert_eq!(COLLISIONS_TOML.a_b_2, 2);
+        assert_eq!(COLLISIONS_TOML.a_b_3, 3);
+
+        let _: collisions_toml::ServerConfig = COLLISIONS_TOML.server_config;
+        let _: collisions_toml::ServerConfig2 = COLLISIONS_TOML.server_config_2;
+        assert_eq!(COLLISIONS_TOML.server_config.port, 80);
+        assert_eq!(COLLISIONS_TOML.server_config_2.port, 8080);
+    }
+
+    #[test]
+    fn test_naming_conventions() {
+        // modules are snake_case, whatever the field naming
+        let _: crate::ConfigNamingToml = NAMING_TOML;
+        let _: naming_toml::ConfigApiGateway = NAMING_TOML.APIGateway;
+        let _: &[naming_toml::ConfigIoPorts] = NAMING_TOML.IOPorts;
+        assert_eq!(NAMING_TOML.HTTPServer, "http");
+        assert_eq!(NAMING_TOML.userName, "alice");
+        assert_eq!(NAMING_TOML.log_level, "info");
+        assert_eq!(NAMING_TOML.APIGateway.maxRetries, 3);
+        assert_eq!(NAMING_TOML.IOPorts[0].portNumber, 8080);
+
+        let _: crate::DefaultNamingTomlToml = DEFAULT_NAMING_TOML;
+        let _: default_naming_toml::APIGatewayToml = DEFAULT_NAMING_TOML.apigateway;
+        let _: &[default_naming_toml::IOPortsToml] = DEFAULT_NAMING_TOML.ioports;
+        assert_eq!(DEFAULT_NAMING_TOML.httpserver, "http");
+        assert_eq!(DEFAULT_NAMING_TOML.apigateway.maxretries, 3);
+
+        // without a prefix, types keep the original names and only modules are snake_case
+        let _: crate::ORIGINAL = ORIGINAL;
+        let _: original::APIGateway = ORIGINAL.APIGateway;
+        let _: &[original::IOPorts] = ORIGINAL.IOPorts;
+        assert_eq!(ORIGINAL.APIGateway.maxRetries, 3);
+    }
+
+    #[test]
+    fn test_namespace() {
+        // the root type is re-exported next to the item
+        let root: crate::CONFIG = crate::CONFIG;
+        let server: crate::cfg_types::config::Server = root.server;
+
+        assert_eq!(root.name, "gateway");
+        assert_eq!(server.host, "localhost");
+        assert_eq!(server.port, 8080);
+
+        // the hand-written module is untouched
+        let limits = config::Limits { min: 0, max: 1 };
+        assert_eq!(limits.max, 1);
+    }
+
+    #[test]
+    fn test_shared_types() {
+        use crate::shared_toml::primary::Db;
+
+        fn port(db: &Db) -> i64 {
+            db.port
+        }
+
+        assert_eq!(port(&SHARED_TOML.primary.db), 5432);
+        assert_eq!(port(&SHARED_TOML.replica.db), 5433);
+        assert_eq!(port(&SHARED_TOML.backup.database), 5434);
+
+        let _: crate::shared_toml::Replica = SHARED_TOML.primary;
+        let _: crate::shared_toml::ReplicaKey = crate::shared_toml::PrimaryKey::Db;
+        let _: crate::shared_toml::backup::database::Pool = SHARED_TOML.primary.db.pool;
+        assert_ne!(
+            SHARED_TOML.primary.db.pool,
+            SHARED_TOML.backup.database.pool
+        );
+        assert_eq!(SHARED_TOML.backup.database.pool.map().get("max"), Some(&5));
+
+        // value types of table maps are named after their key, so they are not shared
+        let _: crate::shared_toml::a::x::XValue = SHARED_TOML.a.x.one;
+        let _: crate::shared_toml::b::y::YValue = SHARED_TOML.b.y.two;
+        assert_eq!(SHARED_TOML.b.y.two.v, 2);
+    }
+
+    #[test]
+    fn test_mapped_tables() {
+        const ALPHA: config::ServerConfig = MAPPED_TOML.servers.alpha;
+        const LIMITS: config::Limits = MAPPED_TOML.limits;
+        const POOL: config::Pool = MAPPED_TOML.pool;
+        const ROUTES: &[config::Route] = MAPPED_TOML.routes;
+
+        assert_eq!(ALPHA.host, "10.0.0.1");
+        assert_eq!(ALPHA.port, 8080);
+        assert_eq!(LIMITS, config::Limits { min: 1, max: 100 });
+        assert_eq!(POOL.timeout_secs, 30);
+        assert_eq!(ROUTES[1].target, "");
+
+        // identical mapped tables are still reduced to a map
+        assert_eq!(MAPPED_TOML.servers.map().get("beta").unwrap().port, 8081);
+    }
+
+    #[test]
+    fn test_derive() {
+        const CONFIG: derived::Config = derived::Config::VALUE;
+
+        assert_eq!(CONFIG.name, "service");
+        assert_eq!(CONFIG.port, 8080);
+        assert_eq!(CONFIG.ratio, 0.5);
+        const { assert!(CONFIG.enabled) };
+        assert_eq!(CONFIG.ids, [1, 2, 3]);
+        assert_eq!(CONFIG.started.date.year, 2024);
+        assert_eq!(CONFIG.max_rate, f64::NEG_INFINITY);
+        assert_eq!(CONFIG.server.max_load, f32::INFINITY);
+        assert_eq!(CONFIG.magic, b"TCFG");
+        assert_eq!(CONFIG.library, c"libssl.so.3");
+        assert_eq!(CONFIG.server.timeout, 30);
+        assert_eq!(CONFIG.server.tls.cert, "cert.pem");
+        assert_eq!(CONFIG.routes[1].path, "/api");
+        assert_eq!(CONFIG.routes[0].weight, 1.5);
+
+        // nested values are converted to their declared field types
+        assert_eq!(CONFIG.routes[1].weight, 2.0);
+        assert_eq!(CONFIG.server.retries, 3u8);
+        assert_eq!(CONFIG.server.interval.as_secs(), 90);
+        assert!(CONFIG.server.bind.is_loopback());
+        assert_eq!(CONFIG.server.library, c"libz.so.1");
+        assert_eq!(CONFIG.server.started.date.day, 2);
+        assert_eq!(CONFIG.server.octets, [10, 0]);
+    }
+
+    #[test]
+    fn test_enum_values() {
+        use enums_toml::{routes::Method, LogLevel};
+
+        const LEVEL: LogLevel = ENUMS_TOML.log_level;
+        assert_eq!(LEVEL, LogLevel::Debug);
+        assert_eq!(LEVEL.as_str(), "debug");
+        assert_eq!(LogLevel::VARIANTS.len(), 5);
+        assert_eq!("warn".parse::<LogLevel>(), Ok(LogLevel::Warn));
+        assert!("degub".parse::<LogLevel>().is_err());
+        assert_eq!(ENUMS_TOML.fallback.to_string(), "warn");
+
+        // inferred values, missing keys default to the first value
+        assert_eq!(Method::VARIANTS, &[Method::Get, Method::Post]);
+        assert_eq!(ENUMS_TOML.routes[1].method, Method::Post);
+        assert_eq!(ENUMS_TOML.routes[2].method, Method::Get);
+    }
+
+    #[test]
+    fn test_flags() {
+        use flags_toml::{users, Capabilities};
+
+        const CAPABILITIES: Capabilities = FLAGS_TOML.capabilities;
+        assert!(CAPABILITIES.contains(Capabilities::READ.union(Capabilities::WRITE)));
+        assert!(!CAPABILITIES.intersects(Capabilities::ADMIN));
+        assert_eq!(CAPABILITIES.bits(), 0b0011);
+        assert_eq!(Capabilities::all().bits(), 0b1111);
+        assert_eq!(CAPABILITIES.complement().bits(), 0b1100);
+        assert!(Capabilities::from_bits(0b10000).is_none());
+
+        // inferred flags, missing keys have no flags set
+        let root = FLAGS_TOML.users[0].capabilities;
+        assert!(root.contains(users::Capabilities::ADMIN));
+        assert_eq!(users::Capabilities::all().bits(), 0b111);
+        assert!(FLAGS_TOML.users[2].capabilities.is_empty());
+    }
 }